@@ -1,9 +1,16 @@
-use crate::ssh::{SshClient, SshConfig};
+#[cfg(feature = "ssh")]
+use crate::ssh::{SshClient, SshConfig, PtySize};
+#[cfg(feature = "query")]
 use crate::query::{QueryParams, QueryResult};
+#[cfg(feature = "export")]
+use crate::query::{ExportTask, ExportTaskResult};
+#[cfg(feature = "export")]
 use crate::export;
-use crate::deploy::{DeployConfig, DeployStatus};
+#[cfg(feature = "deploy")]
+use crate::deploy::{DeployConfig, DeployStatus, TopologyDeployConfig, TopologyDeploySummary, UninstallConfig};
 use serde::Deserialize;
 
+#[cfg(feature = "ssh")]
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SshConfigDto {
@@ -12,20 +19,67 @@ pub struct SshConfigDto {
     pub username: String,
     pub password: Option<String>,
     pub key_file: Option<String>,
+    #[serde(default)]
+    pub use_agent: bool,
+    // 有序的跳板机链（ProxyJump），前端按 bastion -> ... -> target 的顺序传入
+    #[serde(default)]
+    pub jump_hosts: Vec<SshConfigDto>,
+    // 是否对目标主机做 known_hosts 指纹校验（TOFU），默认开启
+    #[serde(default = "default_verify_host_key")]
+    pub verify_host_key: bool,
 }
 
+#[cfg(feature = "ssh")]
+fn default_verify_host_key() -> bool {
+    true
+}
+
+#[cfg(feature = "ssh")]
 #[tauri::command]
-pub async fn ssh_connect(config: SshConfigDto) -> Result<serde_json::Value, String> {
+pub async fn ssh_connect(app: tauri::AppHandle, config: SshConfigDto) -> Result<serde_json::Value, String> {
+    let mut password = config.password.clone();
+    let mut key_file = config.key_file.clone();
+
+    // 如果 DTO 没有携带密码/密钥，尝试从已解锁的凭据库中查找
+    if password.is_none() && key_file.is_none() {
+        match crate::vault::find_credential(&config.host, &config.username).await {
+            Ok(Some((kind, secret))) if kind == "key" => {
+                // 私钥内容解密后写入临时文件（仅当前用户可读），供密钥认证使用
+                match write_temp_key_file(&secret) {
+                    Ok(path) => key_file = Some(path),
+                    Err(e) => eprintln!("写入临时密钥文件失败: {}", e),
+                }
+            }
+            Ok(Some((_, secret))) => password = Some(secret),
+            Ok(None) => {}
+            Err(e) => eprintln!("查询凭据库失败: {}", e),
+        }
+    }
+
+    let jump_hosts = config.jump_hosts.iter().map(|hop| SshConfig {
+        host: hop.host.clone(),
+        port: hop.port,
+        username: hop.username.clone(),
+        password: hop.password.clone(),
+        key_file: hop.key_file.clone(),
+        use_agent: hop.use_agent,
+        jump_hosts: Vec::new(), // 跳板本身暂不支持再嵌套跳板，链式关系由顶层顺序表达
+        verify_host_key: false, // 跳板各跳暂不纳入 known_hosts 校验范围
+    }).collect();
+
     let ssh_config = SshConfig {
         host: config.host.clone(),
         port: config.port,
         username: config.username.clone(),
-        password: config.password.clone(),
-        key_file: config.key_file.clone(),
+        password,
+        key_file,
+        use_agent: config.use_agent,
+        jump_hosts,
+        verify_host_key: config.verify_host_key,
     };
 
-    match SshClient::connect(ssh_config).await {
-        Ok(_) => Ok(serde_json::json!({ "success": true })),
+    match SshClient::connect(&app, ssh_config).await {
+        Ok(session_id) => Ok(serde_json::json!({ "success": true, "sessionId": session_id })),
         Err(e) => Ok(serde_json::json!({
             "success": false,
             "error": e.to_string()
@@ -33,12 +87,102 @@ pub async fn ssh_connect(config: SshConfigDto) -> Result<serde_json::Value, Stri
     }
 }
 
+#[cfg(feature = "ssh")]
 #[tauri::command]
-pub async fn ssh_disconnect() -> Result<(), String> {
-    SshClient::disconnect().await;
+pub async fn ssh_disconnect(session_id: String) -> Result<(), String> {
+    SshClient::disconnect(&session_id).await;
     Ok(())
 }
 
+#[cfg(feature = "ssh")]
+#[tauri::command]
+pub fn ssh_list_known_hosts(app: tauri::AppHandle) -> Result<Vec<crate::ssh::PinnedHostKey>, String> {
+    crate::ssh::known_hosts_list(&app).map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "ssh")]
+#[tauri::command]
+pub fn ssh_forget_known_host(app: tauri::AppHandle, host: String, port: u16) -> Result<(), String> {
+    crate::ssh::known_hosts_forget(&app, &host, port).map_err(|e| e.to_string())
+}
+
+/// 将凭据库中解密出的私钥内容写入一个仅当前用户可读的临时文件
+#[cfg(feature = "ssh")]
+fn write_temp_key_file(key_contents: &str) -> Result<String, String> {
+    let path = std::env::temp_dir().join(format!("remote-tool-key-{}", uuid::Uuid::new_v4()));
+    std::fs::write(&path, key_contents).map_err(|e| format!("写入密钥文件失败: {}", e))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600));
+    }
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub async fn vault_unlock(app: tauri::AppHandle, passphrase: String) -> Result<(), String> {
+    crate::vault::unlock(app, passphrase).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn vault_save_credential(
+    host: String,
+    username: String,
+    kind: String,
+    secret: String,
+) -> Result<(), String> {
+    crate::vault::save_credential(host, username, kind, secret)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn vault_list() -> Result<Vec<crate::vault::CredentialSummary>, String> {
+    crate::vault::list().await.map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "ssh")]
+#[tauri::command]
+pub async fn vault_serve_as_agent() -> Result<String, String> {
+    crate::vault::serve_as_agent().await.map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "ssh")]
+#[tauri::command]
+pub async fn ssh_open_shell(
+    app: tauri::AppHandle,
+    session_id: String,
+    cols: u16,
+    rows: u16,
+    term: Option<String>,
+) -> Result<String, String> {
+    let size = PtySize { cols, rows };
+    SshClient::open_shell(app, &session_id, size, &term.unwrap_or_else(|| "xterm-256color".to_string()))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "ssh")]
+#[tauri::command]
+pub fn shell_write(shell_id: String, data: Vec<u8>) -> Result<(), String> {
+    SshClient::shell_write(&shell_id, data).map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "ssh")]
+#[tauri::command]
+pub fn shell_resize(shell_id: String, cols: u16, rows: u16) -> Result<(), String> {
+    SshClient::shell_resize(&shell_id, cols, rows).map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "ssh")]
+#[tauri::command]
+pub fn shell_close(shell_id: String) -> Result<(), String> {
+    SshClient::shell_close(&shell_id).map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "query")]
 #[tauri::command]
 pub async fn execute_query(
     app: tauri::AppHandle,
@@ -47,33 +191,129 @@ pub async fn execute_query(
     crate::query::execute_query(params, Some(app)).await
 }
 
+/// 强制下一次 `wide_table` 查询跳过结果缓存重新执行，见
+/// [`crate::query::invalidate_wide_table_cache`]
+#[cfg(feature = "query")]
+#[tauri::command]
+pub fn invalidate_query_cache(db_path: String, start_time: i64, end_time: i64) {
+    crate::query::invalidate_wide_table_cache(&db_path, start_time, end_time);
+}
+
+/// `format` 支持 `"csv"`（默认）、`"json"`、`"ndjson"`、`"parquet"`，见 [`crate::export::export`]。
+/// `config_path` 传入时跳过exe目录/项目根目录的自动搜索，固定用这份导出字段配置
+#[cfg(feature = "export")]
 #[tauri::command]
 pub async fn export_to_csv(
     data: serde_json::Value,
     file_path: String,
     query_type: Option<String>,
+    format: Option<String>,
+    config_path: Option<String>,
 ) -> Result<(), String> {
     // 将JSON值反序列化为QueryResult
     let query_result: QueryResult = serde_json::from_value(data)
         .map_err(|e| format!("解析查询结果失败: {}", e))?;
-    
-    export::export_to_csv(query_result, file_path, query_type).await
+
+    export::export(query_result, file_path, query_type, format, config_path).await
+}
+
+/// 重新加载导出字段配置（`csv_export_config.toml`），不传 `config_path` 时按原有
+/// 搜索顺序重新解析，不需要重启应用就能看到编辑后的配置，见 [`crate::export::reload_config`]
+#[cfg(feature = "export")]
+#[tauri::command]
+pub fn reload_export_config(config_path: Option<String>) -> Result<(), String> {
+    export::reload_config(config_path)
+}
+
+/// 流式导出宽表数据，`output_format` 为 `"xlsx"` 时导出原生XLSX，否则默认导出CSV。
+/// `chunk_seconds` 传入时改走分片续传导出（见 [`crate::query::export_wide_table_direct`]）
+#[cfg(feature = "export")]
+#[tauri::command]
+pub async fn export_wide_table_direct(
+    app: tauri::AppHandle,
+    session_id: String,
+    db_path: String,
+    start_time: i64,
+    end_time: i64,
+    output_path: String,
+    output_format: Option<String>,
+    chunk_seconds: Option<i64>,
+) -> Result<usize, String> {
+    crate::query::export_wide_table_direct(
+        &session_id, db_path, start_time, end_time, output_path, output_format, chunk_seconds, Some(app),
+    ).await
 }
 
+/// 流式导出需量数据，`output_format` 语义同 [`export_wide_table_direct`]
+#[cfg(feature = "export")]
 #[tauri::command]
-pub async fn check_deploy_status() -> Result<DeployStatus, String> {
-    crate::deploy::check_deploy_status().await
+pub async fn export_demand_results_direct(
+    app: tauri::AppHandle,
+    session_id: String,
+    db_path: String,
+    start_time: i64,
+    end_time: i64,
+    output_path: String,
+    output_format: Option<String>,
+) -> Result<usize, String> {
+    crate::query::export_demand_results_direct(
+        &session_id, db_path, start_time, end_time, output_path, output_format, Some(app),
+    ).await
 }
 
+/// 批量导出：按顺序跑一串导出任务，单个任务失败不中断其它任务，见
+/// [`crate::query::export_batch`]
+#[cfg(feature = "export")]
+#[tauri::command]
+pub async fn export_batch(
+    app: tauri::AppHandle,
+    session_id: String,
+    tasks: Vec<ExportTask>,
+) -> Result<Vec<ExportTaskResult>, String> {
+    crate::query::export_batch(&session_id, tasks, Some(app)).await
+}
+
+#[cfg(feature = "deploy")]
+#[tauri::command]
+pub async fn check_deploy_status(
+    session_id: String,
+    sudo_password: Option<String>,
+    user_scope: Option<bool>,
+) -> Result<DeployStatus, String> {
+    crate::deploy::check_deploy_status(&session_id, sudo_password.as_deref(), user_scope.unwrap_or(false)).await
+}
+
+#[cfg(feature = "deploy")]
 #[tauri::command]
 pub async fn deploy_application(
     app: tauri::AppHandle,
     config: DeployConfig,
 ) -> Result<serde_json::Value, String> {
     match crate::deploy::deploy_application(Some(app), config).await {
-        Ok(logs) => Ok(serde_json::json!({
+        Ok((logs, log_path)) => Ok(serde_json::json!({
+            "success": true,
+            "logs": logs,
+            "logPath": log_path
+        })),
+        Err(e) => Ok(serde_json::json!({
+            "success": false,
+            "error": e.to_string(),
+            "logs": Vec::<String>::new()
+        })),
+    }
+}
+
+#[cfg(feature = "deploy")]
+#[tauri::command]
+pub async fn uninstall_application(
+    app: tauri::AppHandle,
+    config: UninstallConfig,
+) -> Result<serde_json::Value, String> {
+    match crate::deploy::uninstall_application(Some(app), config).await {
+        Ok((logs, log_path)) => Ok(serde_json::json!({
             "success": true,
-            "logs": logs
+            "logs": logs,
+            "logPath": log_path
         })),
         Err(e) => Ok(serde_json::json!({
             "success": false,
@@ -82,3 +322,68 @@ pub async fn deploy_application(
         })),
     }
 }
+
+#[cfg(feature = "deploy")]
+#[tauri::command]
+pub async fn deploy_to_topology(
+    app: tauri::AppHandle,
+    config: TopologyDeployConfig,
+) -> Result<TopologyDeploySummary, String> {
+    crate::deploy::deploy_to_topology(Some(app), config).await
+}
+
+/// 检查是否有新版本可用（不下载），端点列表在 tauri.conf.json 的
+/// `plugins.updater` 里配置，改端点不需要重新编译
+#[cfg(desktop)]
+#[tauri::command]
+pub async fn check_for_updates(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
+    use tauri_plugin_updater::UpdaterExt;
+
+    let updater = app.updater().map_err(|e| e.to_string())?;
+    match updater.check().await {
+        Ok(Some(update)) => Ok(serde_json::json!({
+            "available": true,
+            "version": update.version,
+            "notes": update.body,
+        })),
+        Ok(None) => Ok(serde_json::json!({ "available": false })),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// 下载并安装已检测到的更新，下载期间持续向前端发 `update://download-progress`
+/// 事件（已下载字节数 / 总字节数），下载结束时再发一条 `finished` 事件；
+/// 安装完成后直接返回，本命令不调用重启
+#[cfg(desktop)]
+#[tauri::command]
+pub async fn install_update(app: tauri::AppHandle) -> Result<(), String> {
+    use tauri::Emitter;
+    use tauri_plugin_updater::UpdaterExt;
+
+    let updater = app.updater().map_err(|e| e.to_string())?;
+    let update = updater
+        .check()
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "没有可用的更新".to_string())?;
+
+    let progress_handle = app.clone();
+    let mut downloaded: usize = 0;
+    update
+        .download_and_install(
+            move |chunk_length, content_length| {
+                downloaded += chunk_length;
+                let _ = progress_handle.emit(
+                    "update://download-progress",
+                    serde_json::json!({ "downloaded": downloaded, "total": content_length }),
+                );
+            },
+            move || {
+                let _ = app.emit("update://download-progress", serde_json::json!({ "finished": true }));
+            },
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
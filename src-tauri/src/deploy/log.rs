@@ -0,0 +1,106 @@
+use crate::ssh::SshClient;
+use anyhow::{Context, Result};
+use chrono::{FixedOffset, Utc};
+use std::io::Write;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+// 返回当前时间（GMT+8）的 "HH:MM:SS" 表示，和 deploy/mod.rs 里的 log_with_time 用同一口径
+fn time_str() -> String {
+    let beijing_tz = FixedOffset::east_opt(8 * 3600).unwrap();
+    Utc::now().with_timezone(&beijing_tz).format("%H:%M:%S").to_string()
+}
+
+/// 结构化的单次部署命令日志：把每条远程命令的命令本身、起止时间、退出码和
+/// 完整 stdout/stderr 追加写入到 app data dir 下的一个文件里，部署结束后
+/// 该文件可以直接导出给用户附到 bug report 里，而不再只是进程存活期间的
+/// `eprintln!("[DEBUG] ...")`。
+pub struct DeployLog {
+    path: PathBuf,
+}
+
+impl DeployLog {
+    /// 创建一份新的部署日志文件：`deploy-<host>-<ts>.log`，文件名里的 `ts`
+    /// 由调用方传入（而不是在这里取当前时间）
+    pub fn create(app: &AppHandle, host: &str, ts: u64) -> Result<Self> {
+        let dir = app.path().app_data_dir().context("无法定位应用数据目录")?;
+        std::fs::create_dir_all(&dir).context("创建应用数据目录失败")?;
+
+        let safe_host: String = host
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+            .collect();
+        let path = dir.join(format!("deploy-{}-{}.log", safe_host, ts));
+
+        std::fs::write(&path, format!("=== 部署日志: {} ({}) ===\n", host, time_str()))
+            .context("创建部署日志文件失败")?;
+
+        Ok(Self { path })
+    }
+
+    /// 日志文件的完整路径，返回给前端用于"导出部署日志"
+    pub fn path(&self) -> String {
+        self.path.to_string_lossy().to_string()
+    }
+
+    fn append(&self, text: &str) {
+        if let Ok(mut file) = std::fs::OpenOptions::new().append(true).open(&self.path) {
+            let _ = file.write_all(text.as_bytes());
+        }
+    }
+
+    /// 记录一条自由格式的说明。用于 ServiceManager 这类刻意把底层命令封装掉的
+    /// 语义化步骤（见 service_manager.rs），这里没有原始命令可记，只记录动作和结果
+    pub fn note(&self, message: &str) {
+        self.append(&format!("[{}] {}\n", time_str(), message));
+    }
+
+    /// 执行一条远程命令并把完整记录（命令、起止时间、退出码、stdout、stderr）追加到日志文件，
+    /// 返回值和 `SshClient::execute_command` 保持一致，调用方原有的 match 逻辑不需要改动
+    pub async fn exec(&self, session_id: &str, command: &str) -> Result<(i32, String, String)> {
+        let start = time_str();
+        let result = SshClient::execute_command(session_id, command).await;
+        let end = time_str();
+
+        match &result {
+            Ok((exit_status, stdout, stderr)) => {
+                self.append(&format!(
+                    "--- [{} -> {}] $ {}\n退出码: {}\nstdout:\n{}\nstderr:\n{}\n",
+                    start, end, command, exit_status, stdout, stderr
+                ));
+            }
+            Err(e) => {
+                self.append(&format!("--- [{} -> {}] $ {}\n执行失败: {}\n", start, end, command, e));
+            }
+        }
+
+        result
+    }
+
+    /// 和 `exec` 一样，但经 `SshClient::execute_sudo` 执行，支持非 NOPASSWD 主机；
+    /// 密码本身不出现在 `command` 里，这里和 `exec` 一样照原样落盘，不需要额外脱敏
+    pub async fn exec_sudo(
+        &self,
+        session_id: &str,
+        command: &str,
+        sudo_password: Option<&str>,
+    ) -> Result<(i32, String, String)> {
+        let start = time_str();
+        let result = SshClient::execute_sudo(session_id, command, sudo_password).await;
+        let end = time_str();
+
+        match &result {
+            Ok((exit_status, stdout, stderr)) => {
+                self.append(&format!(
+                    "--- [{} -> {}] $ {}\n退出码: {}\nstdout:\n{}\nstderr:\n{}\n",
+                    start, end, command, exit_status, stdout, stderr
+                ));
+            }
+            Err(e) => {
+                self.append(&format!("--- [{} -> {}] $ {}\n执行失败: {}\n", start, end, command, e));
+            }
+        }
+
+        result
+    }
+}
@@ -1,11 +1,28 @@
-use crate::ssh::SshClient;
+use crate::ssh::{SshClient, SshConfig};
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
 use tauri::AppHandle;
+use tokio::sync::Semaphore;
 
-#[derive(Debug, Deserialize)]
+mod service_manager;
+mod system_descriptor;
+mod rollback;
+mod log;
+use service_manager::ServiceManager;
+use rollback::BackupSet;
+use log::DeployLog;
+
+const DEFAULT_HEALTH_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_KEEP_BACKUPS: usize = 5;
+
+// 需要 Clone 以便 deploy_to_topology 用同一份模板为每个节点派生出只有
+// session_id 不同的独立 DeployConfig
+#[derive(Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DeployConfig {
+    pub session_id: String,
     pub binary_path: Option<String>,
     pub config_path: Option<String>,
     pub topo_path: Option<String>,
@@ -14,6 +31,106 @@ pub struct DeployConfig {
     pub upload_topo: bool,
     pub use_root: bool,
     pub start_service: bool,
+    // 是否在失败/健康检查不通过时自动回滚到替换前的产物
+    #[serde(default)]
+    pub rollback: bool,
+    // 启动后等待服务进入 active 状态的超时时间；None 时使用默认值
+    pub health_timeout_secs: Option<u64>,
+    // 目标主机 sudo 需要密码时提供；留空则沿用原先"假定 NOPASSWD"的行为
+    #[serde(default)]
+    pub sudo_password: Option<String>,
+    // 部署成功后 .backups/ 下最多保留几份旧快照；None 时使用默认值。
+    // 仅在 rollback 开启时有意义，否则压根不会产生备份
+    pub keep_backups: Option<usize>,
+    // 自定义 unit 参数（重启策略、依赖、环境变量、资源限制）以及是否安装为
+    // 用户级服务；None 时用 UnitConfig::default() 的值，效果和改动前完全一样
+    pub unit: Option<UnitConfig>,
+}
+
+fn default_unit_type() -> String {
+    "simple".to_string()
+}
+
+fn default_restart_policy() -> String {
+    "always".to_string()
+}
+
+fn default_restart_sec() -> u64 {
+    5
+}
+
+// 驱动 systemd unit 生成的可配置项。之前这些全部写死在 service_manager.rs 的
+// format! 块里（Restart=always、RestartSec=5、After=network.target、Type=simple），
+// 现在收拢成一份表单，由 build_systemd_unit 统一渲染
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnitConfig {
+    #[serde(default = "default_unit_type")]
+    pub unit_type: String,
+    #[serde(default = "default_restart_policy")]
+    pub restart: String,
+    #[serde(default = "default_restart_sec")]
+    pub restart_sec: u64,
+    // 除了固定的 network.target 之外，额外需要的 After= 依赖
+    #[serde(default)]
+    pub after: Vec<String>,
+    #[serde(default)]
+    pub wants: Vec<String>,
+    // 用 BTreeMap 而不是 HashMap：生成的 unit 文件里 Environment= 的顺序
+    // 保持稳定，同一份配置每次部署生成的内容完全一致，方便 diff
+    #[serde(default)]
+    pub environment: std::collections::BTreeMap<String, String>,
+    #[serde(default)]
+    pub environment_file: Option<String>,
+    #[serde(default)]
+    pub memory_max: Option<String>,
+    #[serde(default)]
+    pub cpu_quota: Option<String>,
+    // true 时安装为当前登录用户的 systemd --user 服务（~/.config/systemd/user/），
+    // 不需要 sudo，额外开启 lingering 让服务在用户登出后继续运行；
+    // 给部署账号没有 sudo 权限的场景用
+    #[serde(default)]
+    pub user_scope: bool,
+}
+
+impl Default for UnitConfig {
+    fn default() -> Self {
+        Self {
+            unit_type: default_unit_type(),
+            restart: default_restart_policy(),
+            restart_sec: default_restart_sec(),
+            after: Vec::new(),
+            wants: Vec::new(),
+            environment: std::collections::BTreeMap::new(),
+            environment_file: None,
+            memory_max: None,
+            cpu_quota: None,
+            user_scope: false,
+        }
+    }
+}
+
+// 手写 Debug 而不是 derive，避免 sudo_password 被意外打进日志（例如未来有人
+// 调试时 `eprintln!("{:?}", config)`）
+impl std::fmt::Debug for DeployConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeployConfig")
+            .field("session_id", &self.session_id)
+            .field("binary_path", &self.binary_path)
+            .field("config_path", &self.config_path)
+            .field("topo_path", &self.topo_path)
+            .field("upload_binary", &self.upload_binary)
+            .field("upload_config", &self.upload_config)
+            .field("upload_topo", &self.upload_topo)
+            .field("use_root", &self.use_root)
+            .field("start_service", &self.start_service)
+            .field("rollback", &self.rollback)
+            .field("health_timeout_secs", &self.health_timeout_secs)
+            .field("sudo_password", &self.sudo_password.as_ref().map(|_| "<redacted>"))
+            .field("keep_backups", &self.keep_backups)
+            .field("unit", &self.unit)
+            .finish()
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -28,10 +145,31 @@ pub struct DeployStatus {
 const INSTALL_DIR: &str = "/opt/analysis";
 const SERVICE_NAME: &str = "analysis-collector";
 const BINARY_NAME: &str = "analysis-collector";
-const SERVICE_FILE: &str = "/etc/systemd/system/analysis-collector.service";
 const SERVICE_USER: &str = "analysis";
 
-pub async fn check_deploy_status() -> Result<DeployStatus, String> {
+// 挑选本次操作要用的 ServiceManager：user_scope 时直接走 systemd --user，
+// 不经过探测（探测出来的基础实现和 system.toml 描述符都只针对系统级服务）；
+// 否则沿用原有的探测 + 描述符覆盖流程
+async fn select_manager(session_id: &str, user_scope: bool, log: Option<&DeployLog>) -> Box<dyn ServiceManager> {
+    if user_scope {
+        Box::new(service_manager::SystemdUserManager)
+    } else {
+        service_manager::detect(session_id, INSTALL_DIR, log).await
+    }
+}
+
+pub async fn check_deploy_status(session_id: &str, sudo_password: Option<&str>, user_scope: bool) -> Result<DeployStatus, String> {
+    check_deploy_status_logged(session_id, None, sudo_password, user_scope).await
+}
+
+// 供 deploy_application 使用的版本：可选地把每条命令的完整记录追加到部署日志文件里，
+// 这样即使只是更新流程里顺带做的状态检查，也能进到同一份事后可导出的 transcript
+async fn check_deploy_status_logged(
+    session_id: &str,
+    log: Option<&DeployLog>,
+    sudo_password: Option<&str>,
+    user_scope: bool,
+) -> Result<DeployStatus, String> {
     let mut status = DeployStatus {
         installed: false,
         service_exists: false,
@@ -62,130 +200,59 @@ pub async fn check_deploy_status() -> Result<DeployStatus, String> {
         }
     }
     
-    // 辅助函数：从输出中提取状态（处理多行输出，如 'active'/'inactive' 或 'enabled'/'disabled'）
-    fn extract_status(output: &str, positive: &str, negative: &str) -> Option<bool> {
-        let lines: Vec<&str> = output.lines().collect();
-        // 查找包含状态关键字的行
-        for line in &lines {
-            let trimmed = line.trim();
-            if trimmed == positive {
-                return Some(true);
-            } else if trimmed == negative {
-                return Some(false);
-            }
-        }
-        // 如果没有找到明确的结果，检查是否包含关键字
-        let output_lower = output.to_lowercase();
-        if output_lower.contains(positive) && !output_lower.contains(negative) {
-            Some(true)
-        } else if output_lower.contains(negative) {
-            Some(false)
-        } else {
-            None
-        }
-    }
-    
-    // 检查可执行文件
+    // 检查可执行文件。INSTALL_DIR 通常只有 root 可读，普通用户直接 test 可能因为
+    // 权限不足而得到看似"未安装"的假阴性，所以这一步和其它检查一样走 sudo
     let check_binary = format!(
-        "test -f {}/bin/{} && echo 'exists' || echo 'not_exists'",
+        "sudo test -f {}/bin/{} && echo 'exists' || echo 'not_exists'",
         INSTALL_DIR, BINARY_NAME
     );
-    match SshClient::execute_command(&check_binary).await {
-        Ok((exit_status, stdout, stderr)) => {
+    let binary_check_result = match log {
+        Some(l) => l.exec_sudo(session_id, &check_binary, sudo_password).await,
+        None => SshClient::execute_sudo(session_id, &check_binary, sudo_password).await,
+    };
+    match binary_check_result {
+        Ok((_exit_status, stdout, _stderr)) => {
             status.installed = extract_result(&stdout).unwrap_or(false);
-            // 调试信息：记录命令执行结果
-            eprintln!("[DEBUG] 检查可执行文件: 命令='{}', 退出码={}, stdout='{}', stderr='{}', 提取结果={}, 最终结果={}", 
-                check_binary, exit_status, stdout.trim(), stderr.trim(), 
-                extract_result(&stdout).map(|v| v.to_string()).unwrap_or_else(|| "None".to_string()),
-                status.installed);
-        }
-        Err(e) => {
-            eprintln!("[DEBUG] 检查可执行文件失败: 命令='{}', 错误='{}'", check_binary, e);
-        }
-    }
-    
-    // 检查服务文件（使用sudo，因为/etc/systemd/system需要root权限）
-    let check_service_file = format!(
-        "sudo test -f {} && echo 'exists' || echo 'not_exists'",
-        SERVICE_FILE
-    );
-    match SshClient::execute_command(&check_service_file).await {
-        Ok((exit_status, stdout, stderr)) => {
-            status.service_exists = extract_result(&stdout).unwrap_or(false);
-            // 调试信息：记录命令执行结果
-            eprintln!("[DEBUG] 检查服务文件(sudo): 命令='{}', 退出码={}, stdout='{}', stderr='{}', 提取结果={}, 最终结果={}", 
-                check_service_file, exit_status, stdout.trim(), stderr.trim(),
-                extract_result(&stdout).map(|v| v.to_string()).unwrap_or_else(|| "None".to_string()),
-                status.service_exists);
+            if let Some(l) = log {
+                l.note(&format!("检查可执行文件: {}", status.installed));
+            }
         }
         Err(e) => {
-            eprintln!("[DEBUG] 检查服务文件(sudo)失败: 命令='{}', 错误='{}'", check_service_file, e);
-            // 如果sudo失败，尝试不使用sudo（某些系统可能配置了无密码sudo）
-            let check_service_file_no_sudo = format!(
-                "test -f {} && echo 'exists' || echo 'not_exists'",
-                SERVICE_FILE
-            );
-            match SshClient::execute_command(&check_service_file_no_sudo).await {
-                Ok((exit_status, stdout, stderr)) => {
-                    status.service_exists = extract_result(&stdout).unwrap_or(false);
-                    eprintln!("[DEBUG] 检查服务文件(无sudo): 命令='{}', 退出码={}, stdout='{}', stderr='{}', 提取结果={}, 最终结果={}", 
-                        check_service_file_no_sudo, exit_status, stdout.trim(), stderr.trim(),
-                        extract_result(&stdout).map(|v| v.to_string()).unwrap_or_else(|| "None".to_string()),
-                        status.service_exists);
-                }
-                Err(e2) => {
-                    eprintln!("[DEBUG] 检查服务文件(无sudo)失败: 命令='{}', 错误='{}'", check_service_file_no_sudo, e2);
-                }
+            if let Some(l) = log {
+                l.note(&format!("检查可执行文件失败: {}", e));
             }
         }
     }
-    
+
+    // 服务状态统一经由 ServiceManager 查询，这样无论远程是 systemd 还是 OpenRC，
+    // 这四个标志位的含义都是一致的。ServiceManager 刻意不对外暴露底层命令（见
+    // service_manager.rs），所以这里只能记录语义化的检查结果，而不是完整命令回显
+    let manager = select_manager(session_id, user_scope, log).await;
+
+    status.service_exists = manager.unit_exists(session_id, SERVICE_NAME, sudo_password).await.unwrap_or(false);
+    if let Some(l) = log {
+        l.note(&format!("检查服务定义: {}", status.service_exists));
+    }
+
     if status.service_exists {
-        // 检查服务状态
-        let check_active = format!(
-            "systemctl is-active {} 2>/dev/null && echo 'active' || echo 'inactive'",
-            SERVICE_NAME
-        );
-        match SshClient::execute_command(&check_active).await {
-            Ok((exit_status, stdout, stderr)) => {
-                status.service_running = extract_status(&stdout, "active", "inactive").unwrap_or(false);
-                // 调试信息：记录命令执行结果
-                eprintln!("[DEBUG] 检查服务运行状态: 命令='{}', 退出码={}, stdout='{}', stderr='{}', 提取结果={}, 最终结果={}", 
-                    check_active, exit_status, stdout.trim(), stderr.trim(),
-                    extract_status(&stdout, "active", "inactive").map(|v| v.to_string()).unwrap_or_else(|| "None".to_string()),
-                    status.service_running);
-            }
-            Err(e) => {
-                eprintln!("[DEBUG] 检查服务运行状态失败: 命令='{}', 错误='{}'", check_active, e);
-            }
+        status.service_running = manager.is_active(session_id, SERVICE_NAME, sudo_password).await.unwrap_or(false);
+        if let Some(l) = log {
+            l.note(&format!("检查服务运行状态: {}", status.service_running));
         }
-        
-        // 检查服务是否启用
-        let check_enabled = format!(
-            "systemctl is-enabled {} 2>/dev/null && echo 'enabled' || echo 'disabled'",
-            SERVICE_NAME
-        );
-        match SshClient::execute_command(&check_enabled).await {
-            Ok((exit_status, stdout, stderr)) => {
-                status.service_enabled = extract_status(&stdout, "enabled", "disabled").unwrap_or(false);
-                // 调试信息：记录命令执行结果
-                eprintln!("[DEBUG] 检查服务启用状态: 命令='{}', 退出码={}, stdout='{}', stderr='{}', 提取结果={}, 最终结果={}", 
-                    check_enabled, exit_status, stdout.trim(), stderr.trim(),
-                    extract_status(&stdout, "enabled", "disabled").map(|v| v.to_string()).unwrap_or_else(|| "None".to_string()),
-                    status.service_enabled);
-            }
-            Err(e) => {
-                eprintln!("[DEBUG] 检查服务启用状态失败: 命令='{}', 错误='{}'", check_enabled, e);
-            }
+
+        status.service_enabled = manager.is_enabled(session_id, SERVICE_NAME, sudo_password).await.unwrap_or(false);
+        if let Some(l) = log {
+            l.note(&format!("检查服务启用状态: {}", status.service_enabled));
         }
-    } else {
-        eprintln!("[DEBUG] 服务文件不存在，跳过服务状态检查");
+    } else if let Some(l) = log {
+        l.note("服务定义不存在，跳过服务状态检查");
     }
-    
-    // 输出最终状态摘要
-    eprintln!("[DEBUG] 状态检查完成: installed={}, service_exists={}, service_running={}, service_enabled={}", 
-        status.installed, status.service_exists, status.service_running, status.service_enabled);
-    
+
+    if let Some(l) = log {
+        l.note(&format!("状态检查完成: installed={}, service_exists={}, service_running={}, service_enabled={}",
+            status.installed, status.service_exists, status.service_running, status.service_enabled));
+    }
+
     Ok(status)
 }
 
@@ -197,15 +264,21 @@ fn log_with_time(message: &str) -> String {
     format!("[{}] {}", now.format("%H:%M:%S"), message)
 }
 
-// 添加日志并实时发送事件
-fn add_log_and_emit(app_handle: Option<&AppHandle>, logs: &mut Vec<String>, message: &str) {
+// 添加日志并实时发送事件。`host` 标记这条日志属于哪台主机——单机部署时就是
+// 当前会话的目标地址，批量部署（deploy_to_topology）时前端据此区分各节点的
+// 进度矩阵，而不是把所有并发节点的日志混成一条流
+fn add_log_and_emit(app_handle: Option<&AppHandle>, host: Option<&str>, logs: &mut Vec<String>, message: &str) {
     let log_message = log_with_time(message);
     logs.push(log_message.clone());
-    
+
     // 如果提供了 AppHandle，实时发送事件
     if let Some(handle) = app_handle {
         use tauri::Emitter;
-        let _ = handle.emit("deploy-log", &log_message);
+        let payload = serde_json::json!({
+            "message": log_message,
+            "host": host,
+        });
+        let _ = handle.emit("deploy-log", &payload);
     }
 }
 
@@ -234,6 +307,111 @@ fn filter_benign_warnings(text: &str) -> Option<String> {
     Some(trimmed.to_string())
 }
 
+// 回滚失败产物并把已恢复的文件列表拼进错误信息里，供调用方直接 return Err(...)
+async fn rollback_and_fail(session_id: &str, backups: &BackupSet, sudo_password: Option<&str>, err_msg: String, log: Option<&DeployLog>) -> String {
+    let restored = backups.restore_all(session_id, sudo_password, log).await;
+    if restored.is_empty() {
+        err_msg
+    } else {
+        format!("{}\n\n已自动回滚以下文件: {}", err_msg, restored.join(", "))
+    }
+}
+
+// 在 start/restart 命令返回成功之后核实服务是否真的健康：systemctl show 的
+// ActiveState/Result 比退出码更可靠——一个 unit 可能在 start 命令返回之后
+// 几毫秒内又退出，这时退出码依然是 0。返回 Some(err_msg) 表示不健康，调用方
+// 应当按失败处理；返回 None 表示健康，或者查询状态本身出错（不阻断部署）。
+// 没有结构化状态来源的实现（OpenRC、BSD 等）由 health_status 用 is_active
+// 拼出粗略状态，此时服务未运行同样判为不健康
+async fn verify_service_health(
+    app_handle: Option<&AppHandle>,
+    host: &str,
+    logs: &mut Vec<String>,
+    deploy_log: Option<&DeployLog>,
+    manager: &dyn ServiceManager,
+    session_id: &str,
+    sudo_password: Option<&str>,
+) -> Option<String> {
+    add_log_and_emit(app_handle, Some(host), logs, "验证服务状态...");
+    let status_result = manager.health_status(session_id, SERVICE_NAME, sudo_password).await;
+    if let Some(l) = deploy_log {
+        l.note(&format!("查看服务状态: {}", if status_result.is_ok() { "成功" } else { "失败" }));
+    }
+
+    match status_result {
+        Ok(status) => {
+            add_log_and_emit(
+                app_handle,
+                Some(host),
+                logs,
+                &format!(
+                    "  ActiveState={} SubState={} Result={}{}{}",
+                    status.active_state,
+                    status.sub_state,
+                    if status.result.is_empty() { "(不支持)" } else { &status.result },
+                    status.main_pid.map(|p| format!(" MainPID={}", p)).unwrap_or_default(),
+                    status.exec_main_status.map(|c| format!(" ExecMainStatus={}", c)).unwrap_or_default(),
+                ),
+            );
+            if status.is_healthy() {
+                None
+            } else {
+                Some(format!(
+                    "命令已返回成功，但服务状态显示未正常运行: ActiveState={}, Result={}",
+                    status.active_state, status.result
+                ))
+            }
+        }
+        Err(_) => None,
+    }
+}
+
+// 轮询等待服务进入 active 状态，最长等待 timeout_secs 秒；超时返回 false
+async fn wait_until_active(manager: &dyn ServiceManager, session_id: &str, sudo_password: Option<&str>, timeout_secs: u64) -> bool {
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(timeout_secs);
+    loop {
+        if manager.is_active(session_id, SERVICE_NAME, sudo_password).await.unwrap_or(false) {
+            return true;
+        }
+        if std::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+}
+
+// 计算本地文件的 SHA-256，流式读取避免大文件整个加载进内存
+fn sha256_file(path: &str) -> Result<String, String> {
+    use std::io::Read;
+    let mut file = std::fs::File::open(path).map_err(|e| format!("打开文件失败: {}", e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 1024 * 1024];
+    loop {
+        let n = file.read(&mut buf).map_err(|e| format!("读取文件失败: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// 计算远程文件的 SHA-256，解析 sha256sum 输出的第一列
+async fn remote_sha256(session_id: &str, remote_path: &str) -> Result<String, String> {
+    let cmd = format!("sha256sum '{}'", remote_path);
+    let (exit_status, stdout, stderr) = SshClient::execute_command(session_id, &cmd)
+        .await
+        .map_err(|e| format!("计算远程文件校验和失败: {}", e))?;
+    if exit_status != 0 {
+        return Err(format!("计算远程文件校验和失败: {}", stderr.trim()));
+    }
+    stdout
+        .split_whitespace()
+        .next()
+        .map(|s| s.to_string())
+        .ok_or_else(|| "计算远程文件校验和失败: 无法解析 sha256sum 输出".to_string())
+}
+
 // 格式化文件大小
 fn format_file_size(size: u64) -> String {
     if size < 1024 {
@@ -245,40 +423,95 @@ fn format_file_size(size: u64) -> String {
     }
 }
 
-pub async fn deploy_application(app_handle: Option<AppHandle>, config: DeployConfig) -> Result<Vec<String>, String> {
+pub async fn deploy_application(app_handle: Option<AppHandle>, config: DeployConfig) -> Result<(Vec<String>, Option<String>), String> {
+    let session_id = config.session_id.as_str();
+    let sudo_password = config.sudo_password.as_deref();
     let mut logs = Vec::new();
-    
-    add_log_and_emit(app_handle.as_ref(), &mut logs, "=========================================");
-    add_log_and_emit(app_handle.as_ref(), &mut logs, "开始部署流程");
-    add_log_and_emit(app_handle.as_ref(), &mut logs, "=========================================");
-    
+
+    // 目标主机标识：既用作部署日志文件名的一部分，也作为 deploy-log 事件的 host
+    // 字段——批量部署时每个节点用各自的 session_id 调用本函数，这里各自解析出
+    // 各自的地址，天然就能把事件和节点对上号，不需要额外传参
+    let host = SshClient::get_config(session_id)
+        .map(|c| c.host)
+        .unwrap_or_else(|_| session_id.to_string());
+
+    // 每次部署都在 app data dir 下开一份结构化的命令日志，记录完整的命令/时间戳/
+    // 退出码/输出，部署结束后把路径一起返回给前端，供"导出部署日志"使用；
+    // 没有 AppHandle（理论上的非 UI 调用场景）时没法定位 app data dir，退化为不落盘
+    let deploy_log = match app_handle.as_ref() {
+        Some(app) => {
+            let ts = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            match DeployLog::create(app, &host, ts) {
+                Ok(l) => Some(l),
+                Err(e) => {
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 创建部署日志文件失败，本次部署将不落盘记录: {}", e));
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "=========================================");
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "开始部署流程");
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "=========================================");
+
     // 显示部署配置
-    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("部署配置:"));
-    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  - 上传可执行文件: {}", config.upload_binary.unwrap_or(false)));
-    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  - 上传配置文件: {}", config.upload_config));
-    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  - 上传拓扑文件: {}", config.upload_topo));
-    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  - 运行用户: {}", if config.use_root { "root" } else { SERVICE_USER }));
-    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  - 部署后启动服务: {}", config.start_service));
-    
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("部署配置:"));
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  - 上传可执行文件: {}", config.upload_binary.unwrap_or(false)));
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  - 上传配置文件: {}", config.upload_config));
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  - 上传拓扑文件: {}", config.upload_topo));
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  - 运行用户: {}", if config.use_root { "root" } else { SERVICE_USER }));
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  - 部署后启动服务: {}", config.start_service));
+
+    let unit_config = config.unit.clone().unwrap_or_default();
+    if unit_config.user_scope {
+        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  - 服务范围: 用户级（systemd --user）");
+    }
+
     // 检查部署状态
-    add_log_and_emit(app_handle.as_ref(), &mut logs, "检查当前部署状态...");
-    let status = match check_deploy_status().await {
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "检查当前部署状态...");
+    let status = match check_deploy_status_logged(session_id, deploy_log.as_ref(), sudo_password, unit_config.user_scope).await {
         Ok(s) => {
-            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  可执行文件: {}", if s.installed { "已安装" } else { "未安装" }));
-            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  服务文件: {}", if s.service_exists { "存在" } else { "不存在" }));
-            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  服务状态: {}", if s.service_running { "运行中" } else { "未运行" }));
-            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  服务启用: {}", if s.service_enabled { "已启用" } else { "未启用" }));
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  可执行文件: {}", if s.installed { "已安装" } else { "未安装" }));
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  服务文件: {}", if s.service_exists { "存在" } else { "不存在" }));
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  服务状态: {}", if s.service_running { "运行中" } else { "未运行" }));
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  服务启用: {}", if s.service_enabled { "已启用" } else { "未启用" }));
             s
         }
         Err(e) => {
             let err_msg = format!("检查部署状态失败: {}", e);
-            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ⚠️ {}", err_msg));
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ {}", err_msg));
             return Err(err_msg);
         }
     };
-    
+
+    let manager = select_manager(session_id, unit_config.user_scope, deploy_log.as_ref()).await;
+
+    // 开启 rollback 时，在任何破坏性操作之前先准备好本次部署的备份目录，
+    // 之后每替换一个产物前都先把原文件拷贝进去
+    let mut backups = if config.rollback {
+        let ts = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        match BackupSet::create(session_id, INSTALL_DIR, sudo_password, ts).await {
+            Ok(set) => Some(set),
+            Err(e) => {
+                let err_msg = format!("创建备份目录失败，已取消部署: {}", e);
+                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+                return Err(err_msg);
+            }
+        }
+    } else {
+        None
+    };
+
     let is_update = status.installed;
-    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("部署模式: {}", if is_update { "更新" } else { "新部署" }));
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("部署模式: {}", if is_update { "更新" } else { "新部署" }));
     
     // 判断是否需要重启服务
     // 如果只上传配置文件或拓扑文件，且服务正在运行，需要重启服务以加载新配置
@@ -289,48 +522,55 @@ pub async fn deploy_application(app_handle: Option<AppHandle>, config: DeployCon
     
     // 如果是更新（上传可执行文件）或需要重启服务（上传配置文件/拓扑文件），先停止服务
     if (is_update && status.service_running) || need_restart {
-        add_log_and_emit(app_handle.as_ref(), &mut logs, "停止现有服务...");
-        let stop_cmd = format!("sudo systemctl stop {}", SERVICE_NAME);
-        match SshClient::execute_command(&stop_cmd).await {
+        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "停止现有服务...");
+        let stop_result = manager.stop(session_id, SERVICE_NAME, sudo_password).await;
+        if let Some(l) = deploy_log.as_ref() {
+            l.note(&format!("停止服务: {:?}", stop_result.as_ref().map(|(code, _, _)| *code)));
+        }
+        match stop_result {
             Ok((exit_status, stdout, stderr)) => {
                 if exit_status == 0 {
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, "  ✓ 服务已停止");
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ✓ 服务已停止");
                     if let Some(output) = filter_benign_warnings(&stdout) {
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  输出: {}", output));
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  输出: {}", output));
                     }
                 } else {
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ⚠️ 停止服务返回非零退出码: {}", exit_status));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 停止服务返回非零退出码: {}", exit_status));
                     if let Some(error) = filter_benign_warnings(&stderr) {
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  错误: {}", error));
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  错误: {}", error));
                     }
                 }
             }
             Err(e) => {
-                add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✗ 停止服务失败: {}", e));
+                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ 停止服务失败: {}", e));
             }
         }
     }
-    
+
     // 创建目录结构
-    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("创建目录结构: {}/bin", INSTALL_DIR));
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("创建目录结构: {}/bin", INSTALL_DIR));
     let mkdir_cmd = format!("sudo mkdir -p {}/bin", INSTALL_DIR);
-    match SshClient::execute_command(&mkdir_cmd).await {
+    let mkdir_result = match deploy_log.as_ref() {
+        Some(l) => l.exec_sudo(session_id, &mkdir_cmd, sudo_password).await,
+        None => SshClient::execute_sudo(session_id, &mkdir_cmd, sudo_password).await,
+    };
+    match mkdir_result {
             Ok((exit_status, stdout, stderr)) => {
                 if exit_status == 0 {
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, "  ✓ 目录结构创建成功");
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ✓ 目录结构创建成功");
                     if let Some(output) = filter_benign_warnings(&stdout) {
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  输出: {}", output));
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  输出: {}", output));
                     }
                 } else {
                     let filtered_stderr = filter_benign_warnings(&stderr).unwrap_or_else(|| stderr.trim().to_string());
                     let err_msg = format!("创建目录失败: 退出码 {}, 错误: {}", exit_status, filtered_stderr);
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✗ {}", err_msg));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
                     return Err(err_msg);
                 }
             }
         Err(e) => {
             let err_msg = format!("创建目录失败: {}", e);
-            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✗ {}", err_msg));
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
             return Err(err_msg);
         }
     }
@@ -338,459 +578,1033 @@ pub async fn deploy_application(app_handle: Option<AppHandle>, config: DeployCon
     // 上传可执行文件（如果选择）
     if config.upload_binary.unwrap_or(false) {
         if let Some(ref binary_path) = config.binary_path {
-            add_log_and_emit(app_handle.as_ref(), &mut logs, "上传可执行文件...");
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "上传可执行文件...");
             
             // 检查本地文件
             match std::fs::metadata(binary_path) {
                 Ok(metadata) => {
                     let file_size = metadata.len();
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  本地文件: {}", binary_path));
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  文件大小: {}", format_file_size(file_size)));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  本地文件: {}", binary_path));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  文件大小: {}", format_file_size(file_size)));
                 }
                 Err(e) => {
                     let err_msg = format!("无法读取本地文件 {}: {}", binary_path, e);
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✗ {}", err_msg));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
                     return Err(err_msg);
                 }
             }
             
             let temp_remote = format!("/tmp/{}", BINARY_NAME);
-            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  上传到临时位置: {}", temp_remote));
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  上传到临时位置: {}", temp_remote));
             
-            match SshClient::upload_file(binary_path, &temp_remote).await {
+            match SshClient::upload_file(session_id, binary_path, &temp_remote).await {
                 Ok(_) => {
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, "  ✓ 文件上传成功");
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ✓ 文件上传成功");
+
+                    // 校验完整性：本地 SHA-256 与远程 sha256sum 比对，避免传输过程中
+                    // 被截断或损坏而 ls -lh 的文件大小检查又恰好没发现
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "校验文件完整性...");
+                    let local_digest = match sha256_file(binary_path) {
+                        Ok(d) => d,
+                        Err(e) => {
+                            let err_msg = format!("计算本地文件校验和失败: {}", e);
+                            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+                            return Err(err_msg);
+                        }
+                    };
+                    let remote_digest = match remote_sha256(session_id, &temp_remote).await {
+                        Ok(d) => d,
+                        Err(e) => {
+                            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", e));
+                            return Err(e);
+                        }
+                    };
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  本地 SHA-256: {}", local_digest));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  远程 SHA-256: {}", remote_digest));
+                    if !local_digest.eq_ignore_ascii_case(&remote_digest) {
+                        let err_msg = format!("校验和不匹配: 本地 {} 远程 {}", local_digest, remote_digest);
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+                        return Err(err_msg);
+                    }
                 }
                 Err(e) => {
                     let err_msg = format!("上传可执行文件失败: {}", e);
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✗ {}", err_msg));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
                     return Err(err_msg);
                 }
             }
             
             // 验证远程文件
-            add_log_and_emit(app_handle.as_ref(), &mut logs, "验证远程文件...");
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "验证远程文件...");
             let verify_cmd = format!("test -f {} && ls -lh {} | awk '{{print $5}}'", temp_remote, temp_remote);
-            match SshClient::execute_command(&verify_cmd).await {
+            match SshClient::execute_command(session_id, &verify_cmd).await {
                 Ok((_, stdout, _)) => {
                     if let Some(output) = filter_benign_warnings(&stdout) {
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  远程文件大小: {}", output));
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  远程文件大小: {}", output));
                     }
                 }
                 Err(_) => {}
             }
             
+            // 开启了 rollback 时，替换前先把目标位置原有的可执行文件备份一份
+            if let Some(ref mut set) = backups {
+                let target = format!("{}/bin/{}", INSTALL_DIR, BINARY_NAME);
+                if let Err(e) = set.backup_if_exists(session_id, &target, BINARY_NAME, sudo_password).await {
+                    let err_msg = format!("备份现有可执行文件失败，已取消部署: {}", e);
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+                    return Err(err_msg);
+                }
+            }
+
             // 使用 rm -f 确保能覆盖已存在的文件，然后移动并设置权限
-            add_log_and_emit(app_handle.as_ref(), &mut logs, "部署可执行文件到目标位置...");
+            // 这一步在慢速磁盘/大文件上可能耗时较长，优先走流式执行，让 UI 能实时看到输出，
+            // 而不是等命令跑完才一次性显示（没有 AppHandle 时退化为缓冲执行）
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "部署可执行文件到目标位置...");
             let move_cmd = format!(
                 "sudo rm -f '{}/bin/{}' && sudo mv '{}' '{}/bin/{}' && sudo chmod +x '{}/bin/{}' && sudo chown root:root '{}/bin/{}'",
                 INSTALL_DIR, BINARY_NAME, temp_remote, INSTALL_DIR, BINARY_NAME, INSTALL_DIR, BINARY_NAME, INSTALL_DIR, BINARY_NAME
             );
-            match SshClient::execute_command(&move_cmd).await {
+            let move_result = match app_handle.as_ref() {
+                Some(app) => {
+                    // 流式执行的输出已经实时发给前端了，这里没有完整 stdout/stderr 可记，
+                    // 只记一条带退出码的说明
+                    let result = SshClient::execute_streaming(session_id, &move_cmd, app, sudo_password)
+                        .await
+                        .map(|exit_status| (exit_status, String::new(), String::new()));
+                    if let Some(l) = deploy_log.as_ref() {
+                        l.note(&format!("$ {} (流式执行, 退出码={:?})", move_cmd, result.as_ref().map(|(code, _, _)| *code).ok()));
+                    }
+                    result
+                }
+                None => match deploy_log.as_ref() {
+                    Some(l) => l.exec_sudo(session_id, &move_cmd, sudo_password).await,
+                    None => SshClient::execute_sudo(session_id, &move_cmd, sudo_password).await,
+                },
+            };
+            match move_result {
                 Ok((exit_status, stdout, stderr)) => {
                     if exit_status == 0 {
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✓ 可执行文件部署成功: {}/bin/{}", INSTALL_DIR, BINARY_NAME));
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✓ 可执行文件部署成功: {}/bin/{}", INSTALL_DIR, BINARY_NAME));
                         if let Some(output) = filter_benign_warnings(&stdout) {
-                            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  输出: {}", output));
+                            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  输出: {}", output));
                         }
                     } else {
                         let filtered_stderr = filter_benign_warnings(&stderr).unwrap_or_else(|| stderr.trim().to_string());
                         let err_msg = format!("部署可执行文件失败: 退出码 {}, 错误: {}", exit_status, filtered_stderr);
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✗ {}", err_msg));
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+                        let err_msg = match backups {
+                            Some(ref set) => rollback_and_fail(session_id, set, sudo_password, err_msg, deploy_log.as_ref()).await,
+                            None => err_msg,
+                        };
                         return Err(err_msg);
                     }
                 }
                 Err(e) => {
                     let err_msg = format!("部署可执行文件失败: {}", e);
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✗ {}", err_msg));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+                    let err_msg = match backups {
+                        Some(ref set) => rollback_and_fail(session_id, set, sudo_password, err_msg, deploy_log.as_ref()).await,
+                        None => err_msg,
+                    };
                     return Err(err_msg);
                 }
             }
         } else {
-            add_log_and_emit(app_handle.as_ref(), &mut logs, "  ⚠️ 警告: 选择了上传可执行文件但未提供文件路径");
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ⚠️ 警告: 选择了上传可执行文件但未提供文件路径");
         }
     }
     
     // 上传配置文件（如果选择）
     if config.upload_config {
         if let Some(ref config_path) = config.config_path {
-            add_log_and_emit(app_handle.as_ref(), &mut logs, "上传配置文件...");
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "上传配置文件...");
             
             // 检查本地文件
             match std::fs::metadata(config_path) {
                 Ok(metadata) => {
                     let file_size = metadata.len();
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  本地文件: {}", config_path));
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  文件大小: {}", format_file_size(file_size)));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  本地文件: {}", config_path));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  文件大小: {}", format_file_size(file_size)));
                 }
                 Err(e) => {
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ⚠️ 无法读取本地文件 {}: {}", config_path, e));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 无法读取本地文件 {}: {}", config_path, e));
                 }
             }
             
             let temp_config = "/tmp/config.toml";
-            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  上传到临时位置: {}", temp_config));
-            
-            match SshClient::upload_file(config_path, temp_config).await {
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  上传到临时位置: {}", temp_config));
+
+            // 上传成功且校验和匹配才继续部署；失败时沿用该区块一贯的风格（记警告、不中断整体流程）
+            let mut config_verified = false;
+            match SshClient::upload_file(session_id, config_path, temp_config).await {
                 Ok(_) => {
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, "  ✓ 文件上传成功");
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ✓ 文件上传成功");
+
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "校验文件完整性...");
+                    match sha256_file(config_path) {
+                        Ok(local_digest) => match remote_sha256(session_id, temp_config).await {
+                            Ok(remote_digest) => {
+                                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  本地 SHA-256: {}", local_digest));
+                                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  远程 SHA-256: {}", remote_digest));
+                                if local_digest.eq_ignore_ascii_case(&remote_digest) {
+                                    config_verified = true;
+                                } else {
+                                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ 校验和不匹配: 本地 {} 远程 {}", local_digest, remote_digest));
+                                }
+                            }
+                            Err(e) => add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", e)),
+                        },
+                        Err(e) => add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ 计算本地文件校验和失败: {}", e)),
+                    }
                 }
                 Err(e) => {
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✗ 上传配置文件失败: {}", e));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ 上传配置文件失败: {}", e));
                 }
             }
-            
-            // 使用 rm -f 确保能覆盖已存在的文件
-            add_log_and_emit(app_handle.as_ref(), &mut logs, "部署配置文件到目标位置...");
-            let move_config_cmd = format!(
-                "sudo rm -f '{}/config.toml' && sudo mv '{}' '{}/config.toml' && sudo chmod 644 '{}/config.toml'",
-                INSTALL_DIR, temp_config, INSTALL_DIR, INSTALL_DIR
-            );
-            match SshClient::execute_command(&move_config_cmd).await {
-                Ok((exit_status, stdout, stderr)) => {
-                    if exit_status == 0 {
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✓ 配置文件部署成功: {}/config.toml", INSTALL_DIR));
-                        if let Some(output) = filter_benign_warnings(&stdout) {
-                            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  输出: {}", output));
-                        }
-                    } else {
-                        let filtered_stderr = filter_benign_warnings(&stderr).unwrap_or_else(|| stderr.trim().to_string());
-                        if !filtered_stderr.is_empty() {
-                            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ⚠️ 配置文件部署失败: 退出码 {}, 错误: {}", exit_status, filtered_stderr));
-                        }
+
+            if !config_verified {
+                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ⚠️ 配置文件校验未通过，跳过本次部署");
+            } else {
+                // 开启了 rollback 时，替换前先把目标位置原有的配置文件备份一份；
+                // 备份失败按该区块一贯的风格只记警告，不中断部署
+                if let Some(ref mut set) = backups {
+                    let target = format!("{}/config.toml", INSTALL_DIR);
+                    if let Err(e) = set.backup_if_exists(session_id, &target, "config.toml", sudo_password).await {
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 备份现有配置文件失败: {}", e));
                     }
                 }
-                Err(e) => {
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ⚠️ 配置文件部署失败: {}", e));
+
+                // 使用 rm -f 确保能覆盖已存在的文件
+                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "部署配置文件到目标位置...");
+                let move_config_cmd = format!(
+                    "sudo rm -f '{}/config.toml' && sudo mv '{}' '{}/config.toml' && sudo chmod 644 '{}/config.toml'",
+                    INSTALL_DIR, temp_config, INSTALL_DIR, INSTALL_DIR
+                );
+                let move_config_result = match deploy_log.as_ref() {
+                    Some(l) => l.exec_sudo(session_id, &move_config_cmd, sudo_password).await,
+                    None => SshClient::execute_sudo(session_id, &move_config_cmd, sudo_password).await,
+                };
+                match move_config_result {
+                    Ok((exit_status, stdout, stderr)) => {
+                        if exit_status == 0 {
+                            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✓ 配置文件部署成功: {}/config.toml", INSTALL_DIR));
+                            if let Some(output) = filter_benign_warnings(&stdout) {
+                                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  输出: {}", output));
+                            }
+                        } else {
+                            let filtered_stderr = filter_benign_warnings(&stderr).unwrap_or_else(|| stderr.trim().to_string());
+                            if !filtered_stderr.is_empty() {
+                                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 配置文件部署失败: 退出码 {}, 错误: {}", exit_status, filtered_stderr));
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 配置文件部署失败: {}", e));
+                    }
                 }
             }
         } else {
-            add_log_and_emit(app_handle.as_ref(), &mut logs, "  ⚠️ 警告: 选择了上传配置文件但未提供文件路径");
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ⚠️ 警告: 选择了上传配置文件但未提供文件路径");
         }
     }
     
     // 上传拓扑文件（如果选择）
     if config.upload_topo {
         if let Some(ref topo_path) = config.topo_path {
-            add_log_and_emit(app_handle.as_ref(), &mut logs, "上传拓扑文件...");
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "上传拓扑文件...");
             
             // 检查本地文件
             match std::fs::metadata(topo_path) {
                 Ok(metadata) => {
                     let file_size = metadata.len();
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  本地文件: {}", topo_path));
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  文件大小: {}", format_file_size(file_size)));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  本地文件: {}", topo_path));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  文件大小: {}", format_file_size(file_size)));
                 }
                 Err(e) => {
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ⚠️ 无法读取本地文件 {}: {}", topo_path, e));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 无法读取本地文件 {}: {}", topo_path, e));
                 }
             }
             
             let temp_topo = "/tmp/topo.json";
-            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  上传到临时位置: {}", temp_topo));
-            
-            match SshClient::upload_file(topo_path, temp_topo).await {
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  上传到临时位置: {}", temp_topo));
+
+            // 同配置文件：上传成功且校验和匹配才继续部署，失败只记警告、不中断整体流程
+            let mut topo_verified = false;
+            match SshClient::upload_file(session_id, topo_path, temp_topo).await {
                 Ok(_) => {
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, "  ✓ 文件上传成功");
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ✓ 文件上传成功");
+
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "校验文件完整性...");
+                    match sha256_file(topo_path) {
+                        Ok(local_digest) => match remote_sha256(session_id, temp_topo).await {
+                            Ok(remote_digest) => {
+                                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  本地 SHA-256: {}", local_digest));
+                                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  远程 SHA-256: {}", remote_digest));
+                                if local_digest.eq_ignore_ascii_case(&remote_digest) {
+                                    topo_verified = true;
+                                } else {
+                                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ 校验和不匹配: 本地 {} 远程 {}", local_digest, remote_digest));
+                                }
+                            }
+                            Err(e) => add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", e)),
+                        },
+                        Err(e) => add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ 计算本地文件校验和失败: {}", e)),
+                    }
                 }
                 Err(e) => {
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✗ 上传拓扑文件失败: {}", e));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ 上传拓扑文件失败: {}", e));
                 }
             }
-            
-            // 使用 rm -f 确保能覆盖已存在的文件
-            add_log_and_emit(app_handle.as_ref(), &mut logs, "部署拓扑文件到目标位置...");
-            let move_topo_cmd = format!(
-                "sudo rm -f '{}/topo.json' && sudo mv '{}' '{}/topo.json' && sudo chmod 644 '{}/topo.json'",
-                INSTALL_DIR, temp_topo, INSTALL_DIR, INSTALL_DIR
-            );
-            match SshClient::execute_command(&move_topo_cmd).await {
-                Ok((exit_status, stdout, stderr)) => {
-                    if exit_status == 0 {
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✓ 拓扑文件部署成功: {}/topo.json", INSTALL_DIR));
-                        if let Some(output) = filter_benign_warnings(&stdout) {
-                            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  输出: {}", output));
-                        }
-                    } else {
-                        let filtered_stderr = filter_benign_warnings(&stderr).unwrap_or_else(|| stderr.trim().to_string());
-                        if !filtered_stderr.is_empty() {
-                            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ⚠️ 拓扑文件部署失败: 退出码 {}, 错误: {}", exit_status, filtered_stderr));
-                        }
+
+            if !topo_verified {
+                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ⚠️ 拓扑文件校验未通过，跳过本次部署");
+            } else {
+                // 同上，备份失败只记警告，不中断部署
+                if let Some(ref mut set) = backups {
+                    let target = format!("{}/topo.json", INSTALL_DIR);
+                    if let Err(e) = set.backup_if_exists(session_id, &target, "topo.json", sudo_password).await {
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 备份现有拓扑文件失败: {}", e));
                     }
                 }
-                Err(e) => {
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ⚠️ 拓扑文件部署失败: {}", e));
+
+                // 使用 rm -f 确保能覆盖已存在的文件
+                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "部署拓扑文件到目标位置...");
+                let move_topo_cmd = format!(
+                    "sudo rm -f '{}/topo.json' && sudo mv '{}' '{}/topo.json' && sudo chmod 644 '{}/topo.json'",
+                    INSTALL_DIR, temp_topo, INSTALL_DIR, INSTALL_DIR
+                );
+                let move_topo_result = match deploy_log.as_ref() {
+                    Some(l) => l.exec_sudo(session_id, &move_topo_cmd, sudo_password).await,
+                    None => SshClient::execute_sudo(session_id, &move_topo_cmd, sudo_password).await,
+                };
+                match move_topo_result {
+                    Ok((exit_status, stdout, stderr)) => {
+                        if exit_status == 0 {
+                            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✓ 拓扑文件部署成功: {}/topo.json", INSTALL_DIR));
+                            if let Some(output) = filter_benign_warnings(&stdout) {
+                                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  输出: {}", output));
+                            }
+                        } else {
+                            let filtered_stderr = filter_benign_warnings(&stderr).unwrap_or_else(|| stderr.trim().to_string());
+                            if !filtered_stderr.is_empty() {
+                                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 拓扑文件部署失败: 退出码 {}, 错误: {}", exit_status, filtered_stderr));
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 拓扑文件部署失败: {}", e));
+                    }
                 }
             }
         } else {
-            add_log_and_emit(app_handle.as_ref(), &mut logs, "  ⚠️ 警告: 选择了上传拓扑文件但未提供文件路径");
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ⚠️ 警告: 选择了上传拓扑文件但未提供文件路径");
         }
     }
     
     // 设置权限
-    add_log_and_emit(app_handle.as_ref(), &mut logs, "设置权限...");
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "设置权限...");
     if !config.use_root {
         // 创建用户
-        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("创建运行用户: {}", SERVICE_USER));
+        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("创建运行用户: {}", SERVICE_USER));
         let create_user_cmd = format!(
             "id {} 2>/dev/null || sudo useradd -r -s /bin/false {}",
             SERVICE_USER, SERVICE_USER
         );
-        match SshClient::execute_command(&create_user_cmd).await {
+        let create_user_result = match deploy_log.as_ref() {
+            Some(l) => l.exec_sudo(session_id, &create_user_cmd, sudo_password).await,
+            None => SshClient::execute_sudo(session_id, &create_user_cmd, sudo_password).await,
+        };
+        match create_user_result {
             Ok((exit_status, stdout, stderr)) => {
                 if exit_status == 0 {
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✓ 用户 {} 已存在或创建成功", SERVICE_USER));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✓ 用户 {} 已存在或创建成功", SERVICE_USER));
                     if let Some(output) = filter_benign_warnings(&stdout) {
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  输出: {}", output));
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  输出: {}", output));
                     }
                 } else {
                     if let Some(error) = filter_benign_warnings(&stderr) {
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ⚠️ 创建用户失败: 退出码 {}, 错误: {}", exit_status, error));
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 创建用户失败: 退出码 {}, 错误: {}", exit_status, error));
                     }
                 }
             }
             Err(e) => {
-                add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ⚠️ 创建用户失败: {}", e));
+                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 创建用户失败: {}", e));
             }
         }
         
-        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("设置目录所有者: {}:{}", SERVICE_USER, SERVICE_USER));
+        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("设置目录所有者: {}:{}", SERVICE_USER, SERVICE_USER));
         let chown_cmd = format!(
             "sudo chown -R {}:{} {}",
             SERVICE_USER, SERVICE_USER, INSTALL_DIR
         );
-        match SshClient::execute_command(&chown_cmd).await {
+        let chown_result = match deploy_log.as_ref() {
+            Some(l) => l.exec_sudo(session_id, &chown_cmd, sudo_password).await,
+            None => SshClient::execute_sudo(session_id, &chown_cmd, sudo_password).await,
+        };
+        match chown_result {
             Ok((exit_status, stdout, stderr)) => {
                 if exit_status == 0 {
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, "  ✓ 权限设置成功");
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ✓ 权限设置成功");
                     if let Some(output) = filter_benign_warnings(&stdout) {
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  输出: {}", output));
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  输出: {}", output));
                     }
                 } else {
                     if let Some(error) = filter_benign_warnings(&stderr) {
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ⚠️ 权限设置失败: 退出码 {}, 错误: {}", exit_status, error));
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 权限设置失败: 退出码 {}, 错误: {}", exit_status, error));
                     }
                 }
             }
             Err(e) => {
-                add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ⚠️ 权限设置失败: {}", e));
+                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 权限设置失败: {}", e));
             }
         }
     } else {
-        add_log_and_emit(app_handle.as_ref(), &mut logs, "使用 root 用户运行，跳过权限设置");
+        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "使用 root 用户运行，跳过权限设置");
     }
     
-    // 创建服务文件
-    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("创建服务文件: {}", SERVICE_FILE));
-    let service_content = if config.use_root {
-        format!(
-            r#"[Unit]
-Description=Analysis Data Collector
-After=network.target
-
-[Service]
-Type=simple
-WorkingDirectory={}
-ExecStart={}/bin/{} --config {}/config.toml
-Restart=always
-RestartSec=5
-
-[Install]
-WantedBy=multi-user.target"#,
-            INSTALL_DIR, INSTALL_DIR, BINARY_NAME, INSTALL_DIR
-        )
+    // 安装服务定义（systemd 下是 unit 文件，OpenRC 下是 init 脚本），具体格式
+    // 由选中的 ServiceManager 实现决定
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "生成并安装服务定义...");
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  工作目录: {}", INSTALL_DIR));
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  可执行文件: {}/bin/{}", INSTALL_DIR, BINARY_NAME));
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  配置文件: {}/config.toml", INSTALL_DIR));
+
+    let exec_start = format!("{}/bin/{} --config {}/config.toml", INSTALL_DIR, BINARY_NAME, INSTALL_DIR);
+    // 用户级服务本来就跑在当前登录用户自己的 systemd --user 实例下，
+    // User= 指令既无意义也不合法，直接忽略 use_root/SERVICE_USER
+    let service_user = if unit_config.user_scope {
+        None
+    } else if config.use_root {
+        None
     } else {
-        format!(
-            r#"[Unit]
-Description=Analysis Data Collector
-After=network.target
-
-[Service]
-Type=simple
-User={}
-WorkingDirectory={}
-ExecStart={}/bin/{} --config {}/config.toml
-Restart=always
-RestartSec=5
-
-[Install]
-WantedBy=multi-user.target"#,
-            SERVICE_USER, INSTALL_DIR, INSTALL_DIR, BINARY_NAME, INSTALL_DIR
-        )
+        Some(SERVICE_USER)
     };
-    
-    add_log_and_emit(app_handle.as_ref(), &mut logs, "生成服务文件内容...");
-    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  工作目录: {}", INSTALL_DIR));
-    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  可执行文件: {}/bin/{}", INSTALL_DIR, BINARY_NAME));
-    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  配置文件: {}/config.toml", INSTALL_DIR));
-    
-    let temp_service = "/tmp/analysis-collector.service";
-    match std::fs::write(temp_service, &service_content) {
-        Ok(_) => {
-            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✓ 临时服务文件已创建: {}", temp_service));
-        }
-        Err(e) => {
-            let err_msg = format!("创建临时服务文件失败: {}", e);
-            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✗ {}", err_msg));
-            return Err(err_msg);
+
+    // 开启了 rollback 时，覆盖服务定义文件前也备份一份；给不出具体路径
+    // （NullManager、用户级的 SystemdUserManager）时跳过备份，其中 NullManager
+    // 的 install_unit 随后会报错，用户级服务的定义文件则不在备份范围内
+    if let Some(ref mut set) = backups {
+        if let Some(unit_path) = manager.unit_file_path(SERVICE_NAME) {
+            if let Err(e) = set.backup_if_exists(session_id, &unit_path, SERVICE_NAME, sudo_password).await {
+                let err_msg = format!("备份服务定义文件失败，已取消部署: {}", e);
+                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+                return Err(rollback_and_fail(session_id, set, sudo_password, err_msg, deploy_log.as_ref()).await);
+            }
         }
     }
-    
-    add_log_and_emit(app_handle.as_ref(), &mut logs, "上传服务文件...");
-    match SshClient::upload_file(temp_service, temp_service).await {
-        Ok(_) => {
-            add_log_and_emit(app_handle.as_ref(), &mut logs, "  ✓ 服务文件上传成功");
-        }
-        Err(e) => {
-            let err_msg = format!("上传服务文件失败: {}", e);
-            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✗ {}", err_msg));
-            return Err(err_msg);
-        }
+
+    let install_unit_result = manager
+        .install_unit(session_id, SERVICE_NAME, "Analysis Data Collector", INSTALL_DIR, &exec_start, service_user, &unit_config, sudo_password)
+        .await;
+    if let Some(l) = deploy_log.as_ref() {
+        l.note(&format!("安装服务定义: {}", if install_unit_result.is_ok() { "成功" } else { "失败" }));
     }
-    
-    add_log_and_emit(app_handle.as_ref(), &mut logs, "部署服务文件并重新加载 systemd...");
-    let move_service_cmd = format!(
-        "sudo mv '{}' '{}' && sudo systemctl daemon-reload",
-        temp_service, SERVICE_FILE
-    );
-    match SshClient::execute_command(&move_service_cmd).await {
-            Ok((exit_status, stdout, stderr)) => {
-                if exit_status == 0 {
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✓ 服务文件部署成功: {}", SERVICE_FILE));
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, "  ✓ systemd 已重新加载");
-                    if let Some(output) = filter_benign_warnings(&stdout) {
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  输出: {}", output));
-                    }
-                } else {
-                    let filtered_stderr = filter_benign_warnings(&stderr).unwrap_or_else(|| stderr.trim().to_string());
-                    let err_msg = format!("创建服务文件失败: 退出码 {}, 错误: {}", exit_status, filtered_stderr);
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✗ {}", err_msg));
-                    return Err(err_msg);
-                }
-            }
+    match install_unit_result {
+        Ok(()) => {
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ✓ 服务定义安装成功");
+        }
         Err(e) => {
             let err_msg = format!("创建服务文件失败: {}", e);
-            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✗ {}", err_msg));
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+            let err_msg = match backups {
+                Some(ref set) => rollback_and_fail(session_id, set, sudo_password, err_msg, deploy_log.as_ref()).await,
+                None => err_msg,
+            };
             return Err(err_msg);
         }
     }
-    
+
     // 启用并启动/重启服务
     // 如果需要重启服务（上传配置文件或拓扑文件），即使 start_service 为 false 也要重启
     if config.start_service || need_restart {
         if need_restart && !config.start_service {
-            add_log_and_emit(app_handle.as_ref(), &mut logs, "检测到配置文件或拓扑文件更新，需要重启服务以加载新配置...");
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "检测到配置文件或拓扑文件更新，需要重启服务以加载新配置...");
         }
         
-        add_log_and_emit(app_handle.as_ref(), &mut logs, "启用服务...");
-        let enable_cmd = format!("sudo systemctl enable {}", SERVICE_NAME);
-        match SshClient::execute_command(&enable_cmd).await {
+        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "启用服务...");
+        let enable_result = manager.enable(session_id, SERVICE_NAME, sudo_password).await;
+        if let Some(l) = deploy_log.as_ref() {
+            l.note(&format!("启用服务: {:?}", enable_result.as_ref().map(|(code, _, _)| *code).ok()));
+        }
+        match enable_result {
             Ok((exit_status, stdout, stderr)) => {
                 if exit_status == 0 {
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, "  ✓ 服务已启用");
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ✓ 服务已启用");
                     if let Some(output) = filter_benign_warnings(&stdout) {
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  输出: {}", output));
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  输出: {}", output));
                     }
                 } else {
                     if let Some(error) = filter_benign_warnings(&stderr) {
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ⚠️ 启用服务失败: 退出码 {}, 错误: {}", exit_status, error));
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 启用服务失败: 退出码 {}, 错误: {}", exit_status, error));
                     }
                 }
             }
             Err(e) => {
-                add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ⚠️ 启用服务失败: {}", e));
+                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 启用服务失败: {}", e));
             }
         }
         
         // 如果服务之前已经在运行（需要重启），使用 restart；否则使用 start
         if need_restart {
-            add_log_and_emit(app_handle.as_ref(), &mut logs, "重启服务以加载新配置...");
-            let restart_cmd = format!("sudo systemctl restart {}", SERVICE_NAME);
-            match SshClient::execute_command(&restart_cmd).await {
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "重启服务以加载新配置...");
+            let restart_result = manager.restart(session_id, SERVICE_NAME, sudo_password).await;
+            if let Some(l) = deploy_log.as_ref() {
+                l.note(&format!("重启服务: {:?}", restart_result.as_ref().map(|(code, _, _)| *code).ok()));
+            }
+            match restart_result {
                 Ok((exit_status, stdout, stderr)) => {
                     if exit_status == 0 {
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, "  ✓ 服务已重启");
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ✓ 服务已重启");
                         if let Some(output) = filter_benign_warnings(&stdout) {
-                            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  输出: {}", output));
+                            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  输出: {}", output));
                         }
-                        
-                        // 验证服务状态
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, "验证服务状态...");
-                        let status_cmd = format!("sudo systemctl status {} --no-pager -l", SERVICE_NAME);
-                        match SshClient::execute_command(&status_cmd).await {
-                            Ok((_, status_output, _)) => {
-                                if let Some(output) = filter_benign_warnings(&status_output) {
-                                    let status_lines: Vec<&str> = output.lines().take(3).collect();
-                                    for line in status_lines {
-                                        if !line.trim().is_empty() {
-                                            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  {}", line));
-                                        }
-                                    }
-                                }
+
+                        // 验证服务状态：退出码是 0 不代表服务真的健康，用 systemctl show
+                        // 解析出来的 ActiveState/Result 核实一遍，不健康就按失败处理
+                        if let Some(err_msg) = verify_service_health(
+                            app_handle.as_ref(),
+                            &host,
+                            &mut logs,
+                            deploy_log.as_ref(),
+                            manager.as_ref(),
+                            session_id,
+                            sudo_password,
+                        )
+                        .await
+                        {
+                            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+                            let err_msg = match backups {
+                                Some(ref set) => rollback_and_fail(session_id, set, sudo_password, err_msg, deploy_log.as_ref()).await,
+                                None => err_msg,
+                            };
+                            return Err(err_msg);
+                        }
+
+                        // 开启了 rollback 时，重启命令返回 0 不代表服务真的起来了，
+                        // 继续轮询 is_active 直到超时，超时就回滚
+                        if let Some(ref set) = backups {
+                            let timeout = config.health_timeout_secs.unwrap_or(DEFAULT_HEALTH_TIMEOUT_SECS);
+                            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("健康检查: 等待服务进入运行状态（最长 {} 秒）...", timeout));
+                            if wait_until_active(manager.as_ref(), session_id, sudo_password, timeout).await {
+                                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ✓ 健康检查通过");
+                            } else {
+                                let err_msg = format!("健康检查失败: 服务在 {} 秒内未进入运行状态", timeout);
+                                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+                                return Err(rollback_and_fail(session_id, set, sudo_password, err_msg, deploy_log.as_ref()).await);
                             }
-                            Err(_) => {}
                         }
                     } else {
                         let filtered_stderr = filter_benign_warnings(&stderr).unwrap_or_else(|| stderr.trim().to_string());
                         let err_msg = format!("重启服务失败: 退出码 {}, 错误: {}", exit_status, filtered_stderr);
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✗ {}", err_msg));
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+                        let err_msg = match backups {
+                            Some(ref set) => rollback_and_fail(session_id, set, sudo_password, err_msg, deploy_log.as_ref()).await,
+                            None => err_msg,
+                        };
                         return Err(err_msg);
                     }
                 }
                 Err(e) => {
                     let err_msg = format!("重启服务失败: {}", e);
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✗ {}", err_msg));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+                    let err_msg = match backups {
+                        Some(ref set) => rollback_and_fail(session_id, set, sudo_password, err_msg, deploy_log.as_ref()).await,
+                        None => err_msg,
+                    };
                     return Err(err_msg);
                 }
             }
         } else {
-            add_log_and_emit(app_handle.as_ref(), &mut logs, "启动服务...");
-            let start_cmd = format!("sudo systemctl start {}", SERVICE_NAME);
-            match SshClient::execute_command(&start_cmd).await {
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "启动服务...");
+            let start_result = manager.start(session_id, SERVICE_NAME, sudo_password).await;
+            if let Some(l) = deploy_log.as_ref() {
+                l.note(&format!("启动服务: {:?}", start_result.as_ref().map(|(code, _, _)| *code).ok()));
+            }
+            match start_result {
                 Ok((exit_status, stdout, stderr)) => {
                     if exit_status == 0 {
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, "  ✓ 服务已启动");
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ✓ 服务已启动");
                         if let Some(output) = filter_benign_warnings(&stdout) {
-                            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  输出: {}", output));
+                            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  输出: {}", output));
                         }
-                        
-                        // 验证服务状态
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, "验证服务状态...");
-                        let status_cmd = format!("sudo systemctl status {} --no-pager -l", SERVICE_NAME);
-                        match SshClient::execute_command(&status_cmd).await {
-                            Ok((_, status_output, _)) => {
-                                if let Some(output) = filter_benign_warnings(&status_output) {
-                                    let status_lines: Vec<&str> = output.lines().take(3).collect();
-                                    for line in status_lines {
-                                        if !line.trim().is_empty() {
-                                            add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  {}", line));
-                                        }
-                                    }
-                                }
+
+                        // 验证服务状态：同重启分支，退出码 0 不代表服务真的健康
+                        if let Some(err_msg) = verify_service_health(
+                            app_handle.as_ref(),
+                            &host,
+                            &mut logs,
+                            deploy_log.as_ref(),
+                            manager.as_ref(),
+                            session_id,
+                            sudo_password,
+                        )
+                        .await
+                        {
+                            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+                            let err_msg = match backups {
+                                Some(ref set) => rollback_and_fail(session_id, set, sudo_password, err_msg, deploy_log.as_ref()).await,
+                                None => err_msg,
+                            };
+                            return Err(err_msg);
+                        }
+
+                        // 同重启分支：启动命令返回 0 不代表服务真的起来了，开启 rollback 时
+                        // 继续轮询 is_active 直到超时，超时就回滚
+                        if let Some(ref set) = backups {
+                            let timeout = config.health_timeout_secs.unwrap_or(DEFAULT_HEALTH_TIMEOUT_SECS);
+                            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("健康检查: 等待服务进入运行状态（最长 {} 秒）...", timeout));
+                            if wait_until_active(manager.as_ref(), session_id, sudo_password, timeout).await {
+                                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ✓ 健康检查通过");
+                            } else {
+                                let err_msg = format!("健康检查失败: 服务在 {} 秒内未进入运行状态", timeout);
+                                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+                                return Err(rollback_and_fail(session_id, set, sudo_password, err_msg, deploy_log.as_ref()).await);
                             }
-                            Err(_) => {}
                         }
                     } else {
                         let filtered_stderr = filter_benign_warnings(&stderr).unwrap_or_else(|| stderr.trim().to_string());
                         let err_msg = format!("启动服务失败: 退出码 {}, 错误: {}", exit_status, filtered_stderr);
-                        add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✗ {}", err_msg));
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+                        let err_msg = match backups {
+                            Some(ref set) => rollback_and_fail(session_id, set, sudo_password, err_msg, deploy_log.as_ref()).await,
+                            None => err_msg,
+                        };
                         return Err(err_msg);
                     }
                 }
                 Err(e) => {
                     let err_msg = format!("启动服务失败: {}", e);
-                    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("  ✗ {}", err_msg));
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+                    let err_msg = match backups {
+                        Some(ref set) => rollback_and_fail(session_id, set, sudo_password, err_msg, deploy_log.as_ref()).await,
+                        None => err_msg,
+                    };
                     return Err(err_msg);
                 }
             }
         }
     } else {
-        add_log_and_emit(app_handle.as_ref(), &mut logs, "跳过服务启动（未选择启动服务选项）");
+        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "跳过服务启动（未选择启动服务选项）");
     }
     
-    add_log_and_emit(app_handle.as_ref(), &mut logs, "=========================================");
-    add_log_and_emit(app_handle.as_ref(), &mut logs, &format!("{}完成！", if is_update { "更新" } else { "部署" }));
-    add_log_and_emit(app_handle.as_ref(), &mut logs, "=========================================");
-    
-    Ok(logs)
+    // 部署整体成功，清理掉 rollback 过程中积累的旧快照，只保留最近几份，
+    // 避免 .backups/ 目录随着每次部署无限增长
+    if backups.is_some() {
+        let keep = config.keep_backups.unwrap_or(DEFAULT_KEEP_BACKUPS);
+        let pruned = rollback::prune_old_backups(session_id, INSTALL_DIR, sudo_password, keep, deploy_log.as_ref()).await;
+        if !pruned.is_empty() {
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("清理了 {} 份旧备份，保留最近 {} 份", pruned.len(), keep));
+        }
+    }
+
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "=========================================");
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("{}完成！", if is_update { "更新" } else { "部署" }));
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "=========================================");
+
+    Ok((logs, deploy_log.as_ref().map(|l| l.path())))
+}
+
+// ===========================================================================
+// 卸载：和 deploy_application 对称的反操作。停止/禁用服务、删除服务定义、
+// 删除 INSTALL_DIR，可选保留配置和数据、可选回收 SERVICE_USER 账户。
+// ===========================================================================
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UninstallConfig {
+    pub session_id: String,
+    // 保留 config.toml、拓扑文件和其它数据，只删除 bin/ 和服务定义；
+    // 为 false 时整个 INSTALL_DIR（包括 .backups/）都会被删除
+    #[serde(default)]
+    pub preserve_data: bool,
+    // 服务账户不再被其它用途引用时，一并 userdel 掉
+    #[serde(default)]
+    pub remove_service_user: bool,
+    #[serde(default)]
+    pub sudo_password: Option<String>,
+    // 当初是否安装为 systemd --user 服务；决定卸载时用哪个 ServiceManager
+    #[serde(default)]
+    pub user_scope: bool,
+}
+
+pub async fn uninstall_application(app_handle: Option<AppHandle>, config: UninstallConfig) -> Result<(Vec<String>, Option<String>), String> {
+    let session_id = config.session_id.as_str();
+    let sudo_password = config.sudo_password.as_deref();
+    let mut logs = Vec::new();
+
+    let host = SshClient::get_config(session_id)
+        .map(|c| c.host)
+        .unwrap_or_else(|_| session_id.to_string());
+
+    let deploy_log = match app_handle.as_ref() {
+        Some(app) => {
+            let ts = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            match DeployLog::create(app, &host, ts) {
+                Ok(l) => Some(l),
+                Err(e) => {
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 创建卸载日志文件失败，本次卸载将不落盘记录: {}", e));
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "=========================================");
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "开始卸载流程");
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "=========================================");
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  - 保留配置和数据: {}", config.preserve_data));
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  - 回收服务账户: {}", config.remove_service_user));
+
+    let manager = select_manager(session_id, config.user_scope, deploy_log.as_ref()).await;
+
+    // 停止服务：整个卸载流程是幂等的，服务本来就没在跑（或者定义本来就不存在）
+    // 时停止失败都只记警告，不阻断后续步骤
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "停止服务...");
+    match manager.stop(session_id, SERVICE_NAME, sudo_password).await {
+        Ok((exit_status, stdout, stderr)) => {
+            if exit_status == 0 {
+                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ✓ 服务已停止");
+                if let Some(output) = filter_benign_warnings(&stdout) {
+                    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  输出: {}", output));
+                }
+            } else if let Some(error) = filter_benign_warnings(&stderr) {
+                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 停止服务返回非零退出码 {}: {}", exit_status, error));
+            }
+        }
+        Err(e) => {
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 停止服务失败（可能本来就没有在跑）: {}", e));
+        }
+    }
+
+    // 取消开机自启，同样非致命
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "取消开机自启...");
+    match manager.disable(session_id, SERVICE_NAME, sudo_password).await {
+        Ok((exit_status, _, stderr)) => {
+            if exit_status == 0 {
+                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ✓ 已取消开机自启");
+            } else if let Some(error) = filter_benign_warnings(&stderr) {
+                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 取消开机自启返回非零退出码 {}: {}", exit_status, error));
+            }
+        }
+        Err(e) => {
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 取消开机自启失败（可能本来就没有启用）: {}", e));
+        }
+    }
+
+    // 删除服务定义文件（systemd 下顺带 daemon-reload）。这一步关系到"服务是否
+    // 真的被卸载干净"，失败就报错而不是只记警告
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "删除服务定义文件...");
+    if let Err(e) = manager.remove_unit(session_id, SERVICE_NAME, sudo_password).await {
+        let err_msg = format!("删除服务定义文件失败: {}", e);
+        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+        return Err(err_msg);
+    }
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ✓ 服务定义文件已删除");
+
+    // 删除安装目录：保留数据时只删 bin/，否则整个 INSTALL_DIR 一起删掉
+    // （`rm -rf` 对不存在的路径本来就是成功，天然幂等）
+    let rm_cmd = if config.preserve_data {
+        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("删除可执行文件目录（保留配置和数据）: {}/bin", INSTALL_DIR));
+        format!("sudo rm -rf '{}/bin'", INSTALL_DIR)
+    } else {
+        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("删除安装目录: {}", INSTALL_DIR));
+        format!("sudo rm -rf '{}'", INSTALL_DIR)
+    };
+    let rm_result = match deploy_log.as_ref() {
+        Some(l) => l.exec_sudo(session_id, &rm_cmd, sudo_password).await,
+        None => SshClient::execute_sudo(session_id, &rm_cmd, sudo_password).await,
+    };
+    match rm_result {
+        Ok((exit_status, _, stderr)) => {
+            if exit_status == 0 {
+                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  ✓ 删除完成");
+            } else {
+                let filtered_stderr = filter_benign_warnings(&stderr).unwrap_or_else(|| stderr.trim().to_string());
+                let err_msg = format!("删除安装目录失败: 退出码 {}, 错误: {}", exit_status, filtered_stderr);
+                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+                return Err(err_msg);
+            }
+        }
+        Err(e) => {
+            let err_msg = format!("删除安装目录失败: {}", e);
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✗ {}", err_msg));
+            return Err(err_msg);
+        }
+    }
+
+    // 回收服务账户：只在调用方明确要求时才做，且只在账户还存在、没有属主进程
+    // 残留时才真正 userdel，任何一步失败都只记警告，不影响卸载本身的成功与否
+    if config.remove_service_user {
+        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("检查服务账户 {} 是否可以回收...", SERVICE_USER));
+        let check_cmd = format!("id -u {} >/dev/null 2>&1 && echo exists || echo not_exists", SERVICE_USER);
+        let user_exists = match SshClient::execute_command(session_id, &check_cmd).await {
+            Ok((_, stdout, _)) => stdout.trim() == "exists",
+            Err(_) => false,
+        };
+
+        if !user_exists {
+            add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "  账户不存在，跳过");
+        } else {
+            let pgrep_cmd = format!("pgrep -u {} >/dev/null 2>&1 && echo referenced || echo free", SERVICE_USER);
+            let referenced = match SshClient::execute_command(session_id, &pgrep_cmd).await {
+                Ok((_, stdout, _)) => stdout.trim() == "referenced",
+                Err(_) => true, // 探测失败时保守起见当作仍被引用，不要误删
+            };
+
+            if referenced {
+                add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 账户 {} 仍有进程在运行，跳过回收", SERVICE_USER));
+            } else {
+                let userdel_cmd = format!("sudo userdel {}", SERVICE_USER);
+                match SshClient::execute_sudo(session_id, &userdel_cmd, sudo_password).await {
+                    Ok((0, _, _)) => {
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ✓ 已删除服务账户 {}", SERVICE_USER));
+                    }
+                    Ok((exit_status, _, stderr)) => {
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 删除服务账户失败: 退出码 {}, 错误: {}", exit_status, stderr.trim()));
+                    }
+                    Err(e) => {
+                        add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, &format!("  ⚠️ 删除服务账户失败: {}", e));
+                    }
+                }
+            }
+        }
+    }
+
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "=========================================");
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "卸载完成！");
+    add_log_and_emit(app_handle.as_ref(), Some(&host), &mut logs, "=========================================");
+
+    Ok((logs, deploy_log.as_ref().map(|l| l.path())))
+}
+
+// ===========================================================================
+// 拓扑批量部署：解析本地 topo.json 里的节点列表，对每个节点各开一条独立的
+// SSH 会话并发执行 deploy_application，单个节点失败不影响其它节点
+// ===========================================================================
+
+fn default_parallelism() -> usize {
+    4
+}
+
+// 和 DeployConfig 是同一份表单，只是多了 topoPath（既用来解析节点列表，也是
+// 要上传给每个节点的那份 topo.json）和 parallelism；其余字段原样透传给每个
+// 节点各自的 DeployConfig，session_id 由 deploy_to_topology 连接节点后填入
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TopologyDeployConfig {
+    pub topo_path: String,
+    pub binary_path: Option<String>,
+    pub config_path: Option<String>,
+    pub upload_binary: Option<bool>,
+    pub upload_config: bool,
+    pub upload_topo: bool,
+    pub use_root: bool,
+    pub start_service: bool,
+    #[serde(default)]
+    pub rollback: bool,
+    pub health_timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub sudo_password: Option<String>,
+    pub keep_backups: Option<usize>,
+    pub unit: Option<UnitConfig>,
+    // 同时部署的节点数上限，避免一次性打开几十条 SSH 会话
+    #[serde(default = "default_parallelism")]
+    pub parallelism: usize,
+}
+
+// topo.json 里描述节点地址/凭据的部分。这份文件本身就是要上传给每个节点的
+// 拓扑配置，这里顺带把它当作批量部署的目标清单来解析
+#[derive(Deserialize)]
+struct TopologyFile {
+    nodes: Vec<TopologyNode>,
+}
+
+#[derive(Deserialize)]
+struct TopologyNode {
+    host: String,
+    #[serde(default = "default_ssh_port")]
+    port: u16,
+    username: String,
+    password: Option<String>,
+    key_file: Option<String>,
+}
+
+fn default_ssh_port() -> u16 {
+    22
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum NodeDeployOutcome {
+    Succeeded,
+    Failed,
+    // 拿不到该节点的部署结果：节点的部署任务异常终止（例如 panic）。
+    // topo.json 里缺少必要字段的记录会让整份文件解析失败，不会产生 Skipped
+    Skipped,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeDeployResult {
+    pub host: String,
+    pub outcome: NodeDeployOutcome,
+    pub error: Option<String>,
+    pub logs: Vec<String>,
+    pub log_path: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TopologyDeploySummary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub results: Vec<NodeDeployResult>,
+}
+
+fn parse_topology_nodes(topo_path: &str) -> Result<Vec<TopologyNode>, String> {
+    let content = std::fs::read_to_string(topo_path)
+        .map_err(|e| format!("读取拓扑文件失败: {}: {}", topo_path, e))?;
+    let topo: TopologyFile = serde_json::from_str(&content)
+        .map_err(|e| format!("解析拓扑文件失败: {}: {}", topo_path, e))?;
+    Ok(topo.nodes)
+}
+
+// 对单个节点建立连接并执行一次 deploy_application，连接本身失败也归为
+// Failed（已经尝试过、只是没能建立会话），并不算 Skipped
+async fn deploy_one_node(
+    app_handle: Option<AppHandle>,
+    node: TopologyNode,
+    template: &TopologyDeployConfig,
+) -> NodeDeployResult {
+    let host = node.host.clone();
+
+    let ssh_config = SshConfig {
+        host: node.host.clone(),
+        port: node.port,
+        username: node.username,
+        password: node.password,
+        key_file: node.key_file,
+        use_agent: false,
+        jump_hosts: Vec::new(),
+        verify_host_key: true,
+    };
+
+    // 批量部署没有可供弹窗确认主机密钥的地方，known_hosts::verify 走的还是
+    // TOFU（首次连接直接记录指纹），和单机部署里"首次连接自动信任"的行为一致
+    let Some(app) = app_handle.as_ref() else {
+        return NodeDeployResult {
+            host,
+            outcome: NodeDeployOutcome::Failed,
+            error: Some("缺少 AppHandle，无法建立 SSH 连接".to_string()),
+            logs: Vec::new(),
+            log_path: None,
+        };
+    };
+
+    let session_id = match SshClient::connect(app, ssh_config).await {
+        Ok(id) => id,
+        Err(e) => {
+            return NodeDeployResult {
+                host,
+                outcome: NodeDeployOutcome::Failed,
+                error: Some(format!("连接失败: {}", e)),
+                logs: Vec::new(),
+                log_path: None,
+            };
+        }
+    };
+
+    let node_config = DeployConfig {
+        session_id: session_id.clone(),
+        binary_path: template.binary_path.clone(),
+        config_path: template.config_path.clone(),
+        topo_path: Some(template.topo_path.clone()),
+        upload_binary: template.upload_binary,
+        upload_config: template.upload_config,
+        upload_topo: template.upload_topo,
+        use_root: template.use_root,
+        start_service: template.start_service,
+        rollback: template.rollback,
+        health_timeout_secs: template.health_timeout_secs,
+        sudo_password: template.sudo_password.clone(),
+        keep_backups: template.keep_backups,
+        unit: template.unit.clone(),
+    };
+
+    let result = deploy_application(app_handle, node_config).await;
+    SshClient::disconnect(&session_id).await;
+
+    match result {
+        Ok((logs, log_path)) => NodeDeployResult {
+            host,
+            outcome: NodeDeployOutcome::Succeeded,
+            error: None,
+            logs,
+            log_path,
+        },
+        Err(e) => NodeDeployResult {
+            host,
+            outcome: NodeDeployOutcome::Failed,
+            error: Some(e),
+            logs: Vec::new(),
+            log_path: None,
+        },
+    }
+}
+
+/// 解析 `config.topo_path` 里的节点列表，对每个节点并发跑一遍
+/// `deploy_application`，并发数由 `config.parallelism` 限制。单个节点失败
+/// （连接失败或部署失败）都不影响其它节点继续，最终返回每个节点的结果和汇总计数
+pub async fn deploy_to_topology(
+    app_handle: Option<AppHandle>,
+    config: TopologyDeployConfig,
+) -> Result<TopologyDeploySummary, String> {
+    let nodes = parse_topology_nodes(&config.topo_path)?;
+
+    let semaphore = Arc::new(Semaphore::new(config.parallelism.max(1)));
+    let template = Arc::new(config);
+
+    let mut handles = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let semaphore = Arc::clone(&semaphore);
+        let template = Arc::clone(&template);
+        let app_handle = app_handle.clone();
+        handles.push(tokio::spawn(async move {
+            // 信号量从不关闭，acquire 不会失败
+            let _permit = semaphore.acquire().await.unwrap();
+            deploy_one_node(app_handle, node, &template).await
+        }));
+    }
+
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        match handle.await {
+            Ok(result) => results.push(result),
+            Err(e) => results.push(NodeDeployResult {
+                host: "<unknown>".to_string(),
+                outcome: NodeDeployOutcome::Skipped,
+                error: Some(format!("部署任务异常终止: {}", e)),
+                logs: Vec::new(),
+                log_path: None,
+            }),
+        }
+    }
+
+    let succeeded = results.iter().filter(|r| r.outcome == NodeDeployOutcome::Succeeded).count();
+    let failed = results.iter().filter(|r| r.outcome == NodeDeployOutcome::Failed).count();
+    let skipped = results.iter().filter(|r| r.outcome == NodeDeployOutcome::Skipped).count();
+
+    Ok(TopologyDeploySummary {
+        total: results.len(),
+        succeeded,
+        failed,
+        skipped,
+        results,
+    })
 }
@@ -0,0 +1,120 @@
+use super::log::DeployLog;
+use crate::ssh::SshClient;
+use anyhow::Result;
+
+/// 一条已备份的产物：`remote_path` 是部署目标上的原始路径，
+/// `backup_path` 是 `.backups/<ts>/` 下的副本
+pub struct BackupEntry {
+    pub remote_path: String,
+    pub backup_path: String,
+}
+
+/// 本次部署的备份目录，所有被替换的产物在替换前都会拷贝到这里
+pub struct BackupSet {
+    pub backup_dir: String,
+    pub entries: Vec<BackupEntry>,
+}
+
+impl BackupSet {
+    /// 在 `{install_dir}/.backups/<ts>/` 下创建本次部署的备份目录。
+    /// `ts` 由调用方传入（而不是在这里取当前时间），方便单元可控和避免重复调用系统时钟
+    pub async fn create(session_id: &str, install_dir: &str, sudo_password: Option<&str>, ts: u64) -> Result<Self> {
+        let backup_dir = format!("{}/.backups/{}", install_dir, ts);
+        let cmd = format!("sudo mkdir -p '{}'", backup_dir);
+        let (exit_status, _, stderr) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+        if exit_status != 0 {
+            anyhow::bail!("创建备份目录失败: {}", stderr.trim());
+        }
+        Ok(Self { backup_dir, entries: Vec::new() })
+    }
+
+    /// 如果 `remote_path` 存在，拷贝一份到备份目录并记录下来；
+    /// 不存在（例如首次部署）时直接跳过，不算失败
+    pub async fn backup_if_exists(&mut self, session_id: &str, remote_path: &str, backup_name: &str, sudo_password: Option<&str>) -> Result<()> {
+        let check_cmd = format!("sudo test -f '{}' && echo exists || echo not_exists", remote_path);
+        let (_, stdout, _) = SshClient::execute_sudo(session_id, &check_cmd, sudo_password).await?;
+        if stdout.trim() != "exists" {
+            return Ok(());
+        }
+
+        let backup_path = format!("{}/{}", self.backup_dir, backup_name);
+        let cmd = format!("sudo cp -a '{}' '{}'", remote_path, backup_path);
+        let (exit_status, _, stderr) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+        if exit_status != 0 {
+            anyhow::bail!("备份 {} 失败: {}", remote_path, stderr.trim());
+        }
+
+        self.entries.push(BackupEntry {
+            remote_path: remote_path.to_string(),
+            backup_path,
+        });
+        Ok(())
+    }
+
+    /// 按与备份相反的顺序把每个产物恢复到替换前的内容，返回已恢复的远程路径列表，
+    /// 供调用方拼进错误信息里。`log` 传入时，单条恢复失败会记一条说明，而不是只
+    /// 打到进程的stderr上
+    pub async fn restore_all(&self, session_id: &str, sudo_password: Option<&str>, log: Option<&DeployLog>) -> Vec<String> {
+        let mut restored = Vec::new();
+        for entry in self.entries.iter().rev() {
+            let cmd = format!("sudo cp -a '{}' '{}'", entry.backup_path, entry.remote_path);
+            match SshClient::execute_sudo(session_id, &cmd, sudo_password).await {
+                Ok((0, _, _)) => restored.push(entry.remote_path.clone()),
+                Ok((_, _, stderr)) => {
+                    if let Some(l) = log {
+                        l.note(&format!("回滚 {} 失败: {}", entry.remote_path, stderr.trim()));
+                    }
+                }
+                Err(e) => {
+                    if let Some(l) = log {
+                        l.note(&format!("回滚 {} 失败: {}", entry.remote_path, e));
+                    }
+                }
+            }
+        }
+        restored
+    }
+}
+
+/// 部署成功后清理 `{install_dir}/.backups/` 下的旧快照，只保留最近的 `keep` 份。
+/// 目录名是创建时传入的 unix 时间戳，按数值排序即可得到时间顺序；删除失败只记日志，
+/// 不影响本次部署的成功结果
+pub async fn prune_old_backups(session_id: &str, install_dir: &str, sudo_password: Option<&str>, keep: usize, log: Option<&DeployLog>) -> Vec<String> {
+    let backups_dir = format!("{}/.backups", install_dir);
+    let list_cmd = format!("sudo ls -1 '{}' 2>/dev/null", backups_dir);
+    let (_, stdout, _) = match SshClient::execute_sudo(session_id, &list_cmd, sudo_password).await {
+        Ok(result) => result,
+        Err(e) => {
+            if let Some(l) = log {
+                l.note(&format!("列出备份目录失败: {}", e));
+            }
+            return Vec::new();
+        }
+    };
+
+    let mut timestamps: Vec<u64> = stdout.lines().filter_map(|l| l.trim().parse().ok()).collect();
+    timestamps.sort_unstable();
+    if timestamps.len() <= keep {
+        return Vec::new();
+    }
+
+    let mut pruned = Vec::new();
+    for ts in &timestamps[..timestamps.len() - keep] {
+        let dir = format!("{}/{}", backups_dir, ts);
+        let rm_cmd = format!("sudo rm -rf '{}'", dir);
+        match SshClient::execute_sudo(session_id, &rm_cmd, sudo_password).await {
+            Ok((0, _, _)) => pruned.push(dir),
+            Ok((_, _, stderr)) => {
+                if let Some(l) = log {
+                    l.note(&format!("清理旧备份 {} 失败: {}", dir, stderr.trim()));
+                }
+            }
+            Err(e) => {
+                if let Some(l) = log {
+                    l.note(&format!("清理旧备份 {} 失败: {}", dir, e));
+                }
+            }
+        }
+    }
+    pruned
+}
@@ -0,0 +1,901 @@
+use super::log::DeployLog;
+use super::system_descriptor::{self, InitCommands, SystemDescriptor};
+use super::UnitConfig;
+use crate::ssh::SshClient;
+use anyhow::Result;
+
+/// `systemctl show` 解析出来的机器可读服务状态（其它初始化系统没有等价的结构化
+/// 来源时，退化为用 `is_active` 拼凑出同样形状的数据，见 `from_is_active`）。
+/// `ActiveState`/`Result` 分开记录是因为一个 unit 可能在 `start` 命令返回之后
+/// 几毫秒内又因为 `ExecMainStatus` 非零而退出，这种情况下 `ActiveState` 已经
+/// 变成 `failed`，但调用 `systemctl start` 本身的退出码依然是 0
+#[derive(Debug, Clone)]
+pub struct ServiceStatus {
+    pub active_state: String,
+    pub sub_state: String,
+    pub main_pid: Option<u32>,
+    pub exec_main_status: Option<i32>,
+    pub result: String,
+    pub active_enter_timestamp: Option<String>,
+    // 拿不到结构化字段时的原始文本（`systemctl status` 或各发行版自己的 status 输出）
+    pub raw: Option<String>,
+}
+
+impl ServiceStatus {
+    /// 没有结构化状态来源（OpenRC、BSD、NullManager）时的退化构造：只知道
+    /// 服务是否在跑，其余字段留空
+    fn from_is_active(active: bool, raw: Option<String>) -> Self {
+        Self {
+            active_state: if active { "active" } else { "inactive" }.to_string(),
+            sub_state: String::new(),
+            main_pid: None,
+            exec_main_status: None,
+            result: if active { "success" } else { "unknown" }.to_string(),
+            active_enter_timestamp: None,
+            raw,
+        }
+    }
+
+    fn parse_systemctl_show(output: &str) -> Self {
+        let mut fields = std::collections::HashMap::new();
+        for line in output.lines() {
+            if let Some((key, value)) = line.split_once('=') {
+                fields.insert(key, value);
+            }
+        }
+        Self {
+            active_state: fields.get("ActiveState").unwrap_or(&"").to_string(),
+            sub_state: fields.get("SubState").unwrap_or(&"").to_string(),
+            main_pid: fields.get("MainPID").and_then(|v| v.parse().ok()).filter(|p| *p != 0),
+            exec_main_status: fields.get("ExecMainStatus").and_then(|v| v.parse().ok()),
+            result: fields.get("Result").unwrap_or(&"").to_string(),
+            active_enter_timestamp: fields
+                .get("ActiveEnterTimestamp")
+                .map(|v| v.to_string())
+                .filter(|v| !v.is_empty()),
+            raw: Some(output.to_string()),
+        }
+    }
+
+    /// 真正判断"服务是否健康"：`ActiveState` 必须是 `active`，且 `Result`
+    /// （不支持该字段时为空字符串，不算失败）必须是 `success`——这是
+    /// `systemctl start` 返回 0 但服务随后立刻挂掉时唯一能发现的地方
+    pub fn is_healthy(&self) -> bool {
+        self.active_state == "active" && (self.result.is_empty() || self.result == "success")
+    }
+}
+
+/// 服务管理抽象：deploy 模块原先直接写死 `systemctl`/`/etc/systemd/system`，
+/// 导致 OpenRC、SysV、BSD rc 等非 systemd 发行版上静默失败。把"服务是否运行"、
+/// "启/停/启用"、"安装服务定义"这几类操作收敛到这个 trait 后，新增一种初始化系统
+/// 只需要新增一个实现，`check_deploy_status`/`deploy_application` 不需要改动。
+///
+/// 每个方法都带一个 `sudo_password` 参数：目标主机没有 NOPASSWD 时由调用方（最终
+/// 来自 `DeployConfig::sudo_password`）传入，走 `SshClient::execute_sudo` 的密码路径；
+/// 传 `None` 时各实现退化为原有的无密码行为。
+#[async_trait::async_trait]
+pub trait ServiceManager: Send + Sync {
+    /// 服务定义（unit 文件 / init 脚本）是否已存在
+    async fn unit_exists(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<bool>;
+    /// 服务当前是否处于运行状态
+    async fn is_active(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<bool>;
+    /// 服务是否已设置为开机自启
+    async fn is_enabled(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<bool>;
+    async fn stop(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)>;
+    async fn start(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)>;
+    async fn restart(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)>;
+    async fn enable(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)>;
+    /// 取消开机自启，`enable` 的反操作，卸载时使用
+    async fn disable(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)>;
+    /// 重新加载服务配置而不重启进程；不是所有初始化系统/服务都支持，
+    /// 调用方应当把失败当作"这个服务不支持热加载"而不是部署失败
+    async fn reload(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)>;
+    /// 查看服务最近的状态输出（原始文本），`health_status` 拿不到结构化字段时
+    /// 放进 `ServiceStatus::raw`
+    async fn status_summary(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<String>;
+    /// 机器可读的健康状态，用于判断"命令退出码是 0"和"服务真的健康"之间的
+    /// 落差（一个 unit 可能在 start 命令返回之后几毫秒内又退出）。默认实现
+    /// 没有结构化来源可用，退化为用 `is_active` 拼一个粗糙的状态，调用方
+    /// 应当只在 `SystemdManager` 这类有结构化来源的实现上依赖这些字段
+    async fn health_status(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<ServiceStatus> {
+        let active = self.is_active(session_id, name, sudo_password).await.unwrap_or(false);
+        let raw = self.status_summary(session_id, name, sudo_password).await.ok();
+        Ok(ServiceStatus::from_is_active(active, raw))
+    }
+    /// 服务定义文件在远程主机上的路径，安装前用于备份、失败时用于回滚。
+    /// 给不出具体单一文件路径时返回 None（`NullManager`，以及路径依赖运行时
+    /// `$HOME` 的 `SystemdUserManager`），
+    /// 调用方据此跳过备份而不是报错
+    fn unit_file_path(&self, name: &str) -> Option<String> {
+        let _ = name;
+        None
+    }
+    /// 删除服务定义文件并做必要的善后（systemd 下是 `daemon-reload`）。
+    /// 默认实现按 `unit_file_path` 给出的路径 `rm -f`；文件本来就不存在时
+    /// `rm -f` 本身就是成功，卸载流程据此天然幂等
+    async fn remove_unit(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<()> {
+        let Some(path) = self.unit_file_path(name) else {
+            return Ok(());
+        };
+        let cmd = format!("sudo rm -f '{}'", path);
+        let (exit_status, _, stderr) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+        if exit_status != 0 {
+            anyhow::bail!("删除服务定义文件失败: {}", stderr.trim());
+        }
+        Ok(())
+    }
+    /// 安装（或覆盖）服务定义并使其生效
+    async fn install_unit(
+        &self,
+        session_id: &str,
+        name: &str,
+        description: &str,
+        working_dir: &str,
+        exec_start: &str,
+        user: Option<&str>,
+        unit: &UnitConfig,
+        sudo_password: Option<&str>,
+    ) -> Result<()>;
+}
+
+/// 按 `UnitConfig` 渲染 systemd unit 文件内容；`SystemdManager`（系统级）和
+/// `SystemdUserManager`（`systemctl --user`）共用同一套字段，唯一的差异是
+/// `User=` 指令（用户级实例下无意义，调用方应当始终传 `user: None`）和
+/// `WantedBy=`（系统级挂 multi-user.target，用户级挂 default.target）
+fn build_systemd_unit(
+    description: &str,
+    working_dir: &str,
+    exec_start: &str,
+    user: Option<&str>,
+    unit: &UnitConfig,
+    user_scope: bool,
+) -> String {
+    let mut after = vec!["network.target".to_string()];
+    after.extend(unit.after.iter().cloned());
+    let wants_line = if unit.wants.is_empty() {
+        String::new()
+    } else {
+        format!("Wants={}\n", unit.wants.join(" "))
+    };
+    let env_lines: String = unit
+        .environment
+        .iter()
+        .map(|(k, v)| format!("Environment={}={}\n", k, v))
+        .collect();
+    let env_file_line = unit
+        .environment_file
+        .as_ref()
+        .map(|f| format!("EnvironmentFile={}\n", f))
+        .unwrap_or_default();
+    let memory_max_line = unit
+        .memory_max
+        .as_ref()
+        .map(|m| format!("MemoryMax={}\n", m))
+        .unwrap_or_default();
+    let cpu_quota_line = unit
+        .cpu_quota
+        .as_ref()
+        .map(|q| format!("CPUQuota={}\n", q))
+        .unwrap_or_default();
+    let wanted_by = if user_scope { "default.target" } else { "multi-user.target" };
+
+    format!(
+        r#"[Unit]
+Description={}
+After={}
+{}
+[Service]
+Type={}
+{}WorkingDirectory={}
+ExecStart={}
+{}{}{}{}Restart={}
+RestartSec={}
+
+[Install]
+WantedBy={}"#,
+        description,
+        after.join(" "),
+        wants_line,
+        unit.unit_type,
+        user.map(|u| format!("User={}\n", u)).unwrap_or_default(),
+        working_dir,
+        exec_start,
+        env_lines,
+        env_file_line,
+        memory_max_line,
+        cpu_quota_line,
+        unit.restart,
+        unit.restart_sec,
+        wanted_by,
+    )
+}
+
+/// 探测远程主机使用的初始化系统，返回对应的 `ServiceManager` 实现，再按
+/// `{install_dir}/system.toml`（如果存在）里的命令覆盖表包一层
+/// `DescriptorManager`。没有描述符文件时直接返回基础实现，行为和以前完全一样。
+pub async fn detect(session_id: &str, install_dir: &str, log: Option<&DeployLog>) -> Box<dyn ServiceManager> {
+    let base = detect_base(session_id).await;
+    match system_descriptor::load(session_id, install_dir, log).await {
+        Some(descriptor) => Box::new(DescriptorManager { base, descriptor }),
+        None => base,
+    }
+}
+
+/// 探测不到命令覆盖表时使用的基础实现：按 systemd -> OpenRC -> BSD
+/// `service(8)` 的顺序探测，探测不出来（容器、极简发行版等）时退化为
+/// `NullManager`，所有写操作都会返回清晰的"不支持"错误，而不是悄悄假装成功。
+async fn detect_base(session_id: &str) -> Box<dyn ServiceManager> {
+    let probe = "test -d /run/systemd/system && echo systemd || \
+        { command -v rc-service >/dev/null 2>&1 && echo openrc || \
+        { command -v service >/dev/null 2>&1 && echo bsd || echo unknown; }; }";
+    match SshClient::execute_command(session_id, probe).await {
+        Ok((_, stdout, _)) => match stdout.trim() {
+            "systemd" => Box::new(SystemdManager),
+            "openrc" => Box::new(OpenRcManager),
+            "bsd" => Box::new(BsdManager),
+            _ => Box::new(NullManager),
+        },
+        Err(_) => Box::new(NullManager),
+    }
+}
+
+/// 用 `system.toml` 里配置的命令覆盖基础实现的各个动作；没有覆盖某个动作时
+/// 直接把调用转发给 `base`，因此这一层本身不关心远程到底是什么初始化系统
+struct DescriptorManager {
+    base: Box<dyn ServiceManager>,
+    descriptor: SystemDescriptor,
+}
+
+#[async_trait::async_trait]
+impl ServiceManager for DescriptorManager {
+    async fn unit_exists(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<bool> {
+        self.base.unit_exists(session_id, name, sudo_password).await
+    }
+
+    async fn is_active(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<bool> {
+        match InitCommands::render(&self.descriptor.init.is_active, name) {
+            Some(cmd) => {
+                let (exit_status, _, _) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+                Ok(exit_status == 0)
+            }
+            None => self.base.is_active(session_id, name, sudo_password).await,
+        }
+    }
+
+    async fn is_enabled(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<bool> {
+        match InitCommands::render(&self.descriptor.init.is_enabled, name) {
+            Some(cmd) => {
+                let (exit_status, _, _) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+                Ok(exit_status == 0)
+            }
+            None => self.base.is_enabled(session_id, name, sudo_password).await,
+        }
+    }
+
+    async fn stop(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        match InitCommands::render(&self.descriptor.init.stop, name) {
+            Some(cmd) => SshClient::execute_sudo(session_id, &cmd, sudo_password).await,
+            None => self.base.stop(session_id, name, sudo_password).await,
+        }
+    }
+
+    async fn start(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        match InitCommands::render(&self.descriptor.init.boot, name) {
+            Some(cmd) => SshClient::execute_sudo(session_id, &cmd, sudo_password).await,
+            None => self.base.start(session_id, name, sudo_password).await,
+        }
+    }
+
+    async fn restart(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        match InitCommands::render(&self.descriptor.init.restart, name) {
+            Some(cmd) => SshClient::execute_sudo(session_id, &cmd, sudo_password).await,
+            None => self.base.restart(session_id, name, sudo_password).await,
+        }
+    }
+
+    async fn enable(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        match InitCommands::render(&self.descriptor.init.enable, name) {
+            Some(cmd) => SshClient::execute_sudo(session_id, &cmd, sudo_password).await,
+            None => self.base.enable(session_id, name, sudo_password).await,
+        }
+    }
+
+    async fn disable(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        match InitCommands::render(&self.descriptor.init.disable, name) {
+            Some(cmd) => SshClient::execute_sudo(session_id, &cmd, sudo_password).await,
+            None => self.base.disable(session_id, name, sudo_password).await,
+        }
+    }
+
+    async fn reload(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        match InitCommands::render(&self.descriptor.init.reload, name) {
+            Some(cmd) => SshClient::execute_sudo(session_id, &cmd, sudo_password).await,
+            None => self.base.reload(session_id, name, sudo_password).await,
+        }
+    }
+
+    async fn status_summary(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<String> {
+        match InitCommands::render(&self.descriptor.init.status, name) {
+            Some(cmd) => {
+                let (_, stdout, _) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+                Ok(stdout)
+            }
+            None => self.base.status_summary(session_id, name, sudo_password).await,
+        }
+    }
+
+    async fn health_status(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<ServiceStatus> {
+        // system.toml 不描述健康检查，始终交给基础实现（例如 SystemdManager
+        // 的 systemctl show），这样即使配置了其它动作的覆盖命令，也不会丢失
+        // 结构化健康状态
+        self.base.health_status(session_id, name, sudo_password).await
+    }
+
+    async fn install_unit(
+        &self,
+        session_id: &str,
+        name: &str,
+        description: &str,
+        working_dir: &str,
+        exec_start: &str,
+        user: Option<&str>,
+        unit: &UnitConfig,
+        sudo_password: Option<&str>,
+    ) -> Result<()> {
+        // system.toml 目前只描述动作命令，不描述服务定义本身的格式，
+        // 安装服务定义始终交给探测出来的基础实现
+        self.base
+            .install_unit(session_id, name, description, working_dir, exec_start, user, unit, sudo_password)
+            .await
+    }
+
+    fn unit_file_path(&self, name: &str) -> Option<String> {
+        self.base.unit_file_path(name)
+    }
+
+    async fn remove_unit(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<()> {
+        // 同 install_unit：system.toml 不描述服务定义本身的格式，删除也始终
+        // 交给基础实现，这样 systemd 下仍然会触发 daemon-reload
+        self.base.remove_unit(session_id, name, sudo_password).await
+    }
+}
+
+pub struct SystemdManager;
+
+#[async_trait::async_trait]
+impl ServiceManager for SystemdManager {
+    async fn unit_exists(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<bool> {
+        let cmd = format!("sudo test -f /etc/systemd/system/{}.service && echo exists || echo not_exists", name);
+        let (_, stdout, _) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+        Ok(stdout.trim() == "exists")
+    }
+
+    async fn is_active(&self, session_id: &str, name: &str, _sudo_password: Option<&str>) -> Result<bool> {
+        let cmd = format!("systemctl is-active {} 2>/dev/null", name);
+        let (_, stdout, _) = SshClient::execute_command(session_id, &cmd).await?;
+        Ok(stdout.trim() == "active")
+    }
+
+    async fn is_enabled(&self, session_id: &str, name: &str, _sudo_password: Option<&str>) -> Result<bool> {
+        let cmd = format!("systemctl is-enabled {} 2>/dev/null", name);
+        let (_, stdout, _) = SshClient::execute_command(session_id, &cmd).await?;
+        Ok(stdout.trim() == "enabled")
+    }
+
+    async fn stop(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo systemctl stop {}", name), sudo_password).await
+    }
+
+    async fn start(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo systemctl start {}", name), sudo_password).await
+    }
+
+    async fn restart(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo systemctl restart {}", name), sudo_password).await
+    }
+
+    async fn enable(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo systemctl enable {}", name), sudo_password).await
+    }
+
+    async fn disable(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo systemctl disable {}", name), sudo_password).await
+    }
+
+    async fn reload(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo systemctl reload {}", name), sudo_password).await
+    }
+
+    async fn status_summary(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<String> {
+        let cmd = format!("sudo systemctl status {} --no-pager -l", name);
+        let (_, stdout, _) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+        Ok(stdout)
+    }
+
+    async fn health_status(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<ServiceStatus> {
+        let cmd = format!(
+            "sudo systemctl show {} --property=ActiveState,SubState,MainPID,ExecMainStatus,Result,ActiveEnterTimestamp",
+            name
+        );
+        let (exit_status, stdout, _) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+        if exit_status != 0 || stdout.trim().is_empty() {
+            // 极旧版本的 systemd 没有 `systemctl show`，退化成和其它初始化系统
+            // 一样用 is_active 拼一个粗糙的状态
+            let active = self.is_active(session_id, name, sudo_password).await.unwrap_or(false);
+            let raw = self.status_summary(session_id, name, sudo_password).await.ok();
+            return Ok(ServiceStatus::from_is_active(active, raw));
+        }
+        Ok(ServiceStatus::parse_systemctl_show(&stdout))
+    }
+
+    fn unit_file_path(&self, name: &str) -> Option<String> {
+        Some(format!("/etc/systemd/system/{}.service", name))
+    }
+
+    async fn remove_unit(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<()> {
+        let cmd = format!("sudo rm -f '/etc/systemd/system/{}.service' && sudo systemctl daemon-reload", name);
+        let (exit_status, _, stderr) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+        if exit_status != 0 {
+            anyhow::bail!("删除服务定义文件失败: {}", stderr.trim());
+        }
+        Ok(())
+    }
+
+    async fn install_unit(
+        &self,
+        session_id: &str,
+        name: &str,
+        description: &str,
+        working_dir: &str,
+        exec_start: &str,
+        user: Option<&str>,
+        unit: &UnitConfig,
+        sudo_password: Option<&str>,
+    ) -> Result<()> {
+        let unit_content = build_systemd_unit(description, working_dir, exec_start, user, unit, false);
+
+        let temp_unit = format!("/tmp/{}.service", name);
+        std::fs::write(&temp_unit, unit_content)?;
+        SshClient::upload_file(session_id, &temp_unit, &temp_unit).await?;
+        let cmd = format!(
+            "sudo mv '{}' '/etc/systemd/system/{}.service' && sudo systemctl daemon-reload",
+            temp_unit, name
+        );
+        let (exit_status, _, stderr) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+        let _ = std::fs::remove_file(&temp_unit);
+        if exit_status != 0 {
+            anyhow::bail!("安装 systemd 服务失败: {}", stderr.trim());
+        }
+        Ok(())
+    }
+}
+
+pub struct OpenRcManager;
+
+#[async_trait::async_trait]
+impl ServiceManager for OpenRcManager {
+    async fn unit_exists(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<bool> {
+        let cmd = format!("sudo test -f /etc/init.d/{} && echo exists || echo not_exists", name);
+        let (_, stdout, _) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+        Ok(stdout.trim() == "exists")
+    }
+
+    async fn is_active(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<bool> {
+        let cmd = format!("sudo rc-service {} status 2>/dev/null", name);
+        let (_, stdout, _) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+        Ok(stdout.contains("started"))
+    }
+
+    async fn is_enabled(&self, session_id: &str, name: &str, _sudo_password: Option<&str>) -> Result<bool> {
+        let cmd = format!("rc-update show default 2>/dev/null | grep -qw {} && echo enabled || echo disabled", name);
+        let (_, stdout, _) = SshClient::execute_command(session_id, &cmd).await?;
+        Ok(stdout.trim() == "enabled")
+    }
+
+    async fn stop(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo rc-service {} stop", name), sudo_password).await
+    }
+
+    async fn start(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo rc-service {} start", name), sudo_password).await
+    }
+
+    async fn restart(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo rc-service {} restart", name), sudo_password).await
+    }
+
+    async fn enable(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo rc-update add {} default", name), sudo_password).await
+    }
+
+    async fn disable(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo rc-update del {} default", name), sudo_password).await
+    }
+
+    async fn reload(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo rc-service {} reload", name), sudo_password).await
+    }
+
+    async fn status_summary(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<String> {
+        let cmd = format!("sudo rc-service {} status", name);
+        let (_, stdout, _) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+        Ok(stdout)
+    }
+
+    fn unit_file_path(&self, name: &str) -> Option<String> {
+        Some(format!("/etc/init.d/{}", name))
+    }
+
+    async fn install_unit(
+        &self,
+        session_id: &str,
+        name: &str,
+        description: &str,
+        working_dir: &str,
+        exec_start: &str,
+        user: Option<&str>,
+        _unit: &UnitConfig,
+        sudo_password: Option<&str>,
+    ) -> Result<()> {
+        // OpenRC 的 init 脚本没有内建 WorkingDirectory 概念，用 start-stop-daemon 的
+        // --chdir 选项代替；同理用 --user 代替 systemd 的 User=。UnitConfig 里
+        // 重启策略/资源限制这些声明式字段在 OpenRC 下没有直接等价物，暂不支持
+        let script_content = format!(
+            r#"#!/sbin/openrc-run
+description="{}"
+command="{}"
+command_args=""
+command_background=true
+pidfile="/run/{}.pid"
+directory="{}"
+{}
+
+depend() {{
+    need net
+}}
+"#,
+            description,
+            exec_start,
+            name,
+            working_dir,
+            user.map(|u| format!("command_user=\"{}\"", u)).unwrap_or_default(),
+        );
+
+        let temp_script = format!("/tmp/{}.initd", name);
+        std::fs::write(&temp_script, script_content)?;
+        SshClient::upload_file(session_id, &temp_script, &temp_script).await?;
+        let cmd = format!(
+            "sudo mv '{}' '/etc/init.d/{}' && sudo chmod +x '/etc/init.d/{}'",
+            temp_script, name, name
+        );
+        let (exit_status, _, stderr) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+        let _ = std::fs::remove_file(&temp_script);
+        if exit_status != 0 {
+            anyhow::bail!("安装 OpenRC 服务失败: {}", stderr.trim());
+        }
+        Ok(())
+    }
+}
+
+/// BSD `service(8)` + `rc.conf`（FreeBSD/NetBSD 等）。没有 systemd 那种统一
+/// 的 is-active/is-enabled 子命令，`status` 的输出和 `rc.conf` 里的
+/// `{name}_enable` 取值就是唯一的信息来源
+pub struct BsdManager;
+
+#[async_trait::async_trait]
+impl ServiceManager for BsdManager {
+    async fn unit_exists(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<bool> {
+        let cmd = format!(
+            "sudo test -f /usr/local/etc/rc.d/{} -o -f /etc/rc.d/{} && echo exists || echo not_exists",
+            name, name
+        );
+        let (_, stdout, _) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+        Ok(stdout.trim() == "exists")
+    }
+
+    async fn is_active(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<bool> {
+        let cmd = format!("sudo service {} status >/dev/null 2>&1 && echo active || echo inactive", name);
+        let (_, stdout, _) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+        Ok(stdout.trim() == "active")
+    }
+
+    async fn is_enabled(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<bool> {
+        let cmd = format!("sudo sysrc -n {}_enable 2>/dev/null", name);
+        let (_, stdout, _) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+        Ok(stdout.trim().eq_ignore_ascii_case("yes"))
+    }
+
+    async fn stop(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo service {} stop", name), sudo_password).await
+    }
+
+    async fn start(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo service {} start", name), sudo_password).await
+    }
+
+    async fn restart(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo service {} restart", name), sudo_password).await
+    }
+
+    async fn enable(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo sysrc {}_enable=YES", name), sudo_password).await
+    }
+
+    async fn disable(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo sysrc {}_enable=NO", name), sudo_password).await
+    }
+
+    async fn reload(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_sudo(session_id, &format!("sudo service {} reload", name), sudo_password).await
+    }
+
+    async fn status_summary(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<String> {
+        let cmd = format!("sudo service {} status", name);
+        let (_, stdout, _) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+        Ok(stdout)
+    }
+
+    fn unit_file_path(&self, name: &str) -> Option<String> {
+        // install_unit 只往 /usr/local/etc/rc.d 写，/etc/rc.d 只在 unit_exists 里
+        // 作为"系统自带脚本"的兼容探测路径，不是我们会覆盖的文件
+        Some(format!("/usr/local/etc/rc.d/{}", name))
+    }
+
+    async fn install_unit(
+        &self,
+        session_id: &str,
+        name: &str,
+        description: &str,
+        working_dir: &str,
+        exec_start: &str,
+        user: Option<&str>,
+        _unit: &UnitConfig,
+        sudo_password: Option<&str>,
+    ) -> Result<()> {
+        // FreeBSD rc.d 脚本：没有 systemd unit 那种声明式的 WorkingDirectory/User
+        // 字段，借助 daemon(8) 后台化，再用一层 sh -c 实现切换目录后再执行
+        let script_content = format!(
+            r#"#!/bin/sh
+# PROVIDE: {name}
+# REQUIRE: NETWORKING
+# KEYWORD: shutdown
+# {description}
+
+. /etc/rc.subr
+
+name="{name}"
+rcvar="{name}_enable"
+pidfile="/var/run/${{name}}.pid"
+command="/usr/sbin/daemon"
+command_args="-u {user} -P ${{pidfile}} /bin/sh -c \"cd '{working_dir}' && exec {exec_start}\""
+
+load_rc_config $name
+run_rc_command "$1"
+"#,
+            name = name,
+            description = description,
+            user = user.unwrap_or("root"),
+            working_dir = working_dir,
+            exec_start = exec_start,
+        );
+
+        let temp_script = format!("/tmp/{}.rc", name);
+        std::fs::write(&temp_script, script_content)?;
+        SshClient::upload_file(session_id, &temp_script, &temp_script).await?;
+        let cmd = format!(
+            "sudo mv '{}' '/usr/local/etc/rc.d/{}' && sudo chmod +x '/usr/local/etc/rc.d/{}'",
+            temp_script, name, name
+        );
+        let (exit_status, _, stderr) = SshClient::execute_sudo(session_id, &cmd, sudo_password).await?;
+        let _ = std::fs::remove_file(&temp_script);
+        if exit_status != 0 {
+            anyhow::bail!("安装 BSD rc.d 服务失败: {}", stderr.trim());
+        }
+        Ok(())
+    }
+}
+
+/// 探测不出初始化系统时的兜底实现：所有写操作都明确报错，避免悄悄假装成功
+pub struct NullManager;
+
+#[async_trait::async_trait]
+impl ServiceManager for NullManager {
+    async fn unit_exists(&self, _session_id: &str, _name: &str, _sudo_password: Option<&str>) -> Result<bool> {
+        Ok(false)
+    }
+
+    async fn is_active(&self, _session_id: &str, _name: &str, _sudo_password: Option<&str>) -> Result<bool> {
+        Ok(false)
+    }
+
+    async fn is_enabled(&self, _session_id: &str, _name: &str, _sudo_password: Option<&str>) -> Result<bool> {
+        Ok(false)
+    }
+
+    async fn stop(&self, _session_id: &str, _name: &str, _sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        anyhow::bail!("无法识别远程主机的初始化系统，跳过服务停止操作")
+    }
+
+    async fn start(&self, _session_id: &str, _name: &str, _sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        anyhow::bail!("无法识别远程主机的初始化系统，跳过服务启动操作")
+    }
+
+    async fn restart(&self, _session_id: &str, _name: &str, _sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        anyhow::bail!("无法识别远程主机的初始化系统，跳过服务重启操作")
+    }
+
+    async fn enable(&self, _session_id: &str, _name: &str, _sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        anyhow::bail!("无法识别远程主机的初始化系统，跳过服务启用操作")
+    }
+
+    async fn disable(&self, _session_id: &str, _name: &str, _sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        anyhow::bail!("无法识别远程主机的初始化系统，跳过服务禁用操作")
+    }
+
+    async fn reload(&self, _session_id: &str, _name: &str, _sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        anyhow::bail!("无法识别远程主机的初始化系统，跳过服务重新加载操作")
+    }
+
+    async fn status_summary(&self, _session_id: &str, _name: &str, _sudo_password: Option<&str>) -> Result<String> {
+        Ok(String::new())
+    }
+
+    async fn install_unit(
+        &self,
+        _session_id: &str,
+        _name: &str,
+        _description: &str,
+        _working_dir: &str,
+        _exec_start: &str,
+        _user: Option<&str>,
+        _unit: &UnitConfig,
+        _sudo_password: Option<&str>,
+    ) -> Result<()> {
+        anyhow::bail!("无法识别远程主机的初始化系统（既非 systemd 也非 OpenRC），请手动安装服务")
+    }
+}
+
+/// 用户级 systemd 服务（`systemctl --user`）：单元文件放在当前登录用户自己的
+/// `~/.config/systemd/user/` 下，所有命令都不经过 sudo——这类服务本来就不需要
+/// root 权限，强行加 sudo 反而会操作到 root 自己的 systemd --user 实例上。
+/// 远程主机必须已经为该用户开启了 linger（否则用户一断开 SSH，`systemd --user`
+/// 实例就会被回收，服务随之停止），`install_unit` 里会尝试自动开启一次。
+pub struct SystemdUserManager;
+
+impl SystemdUserManager {
+    /// 解析远程登录用户的家目录，单元文件路径依赖这个值，无法像系统级服务
+    /// 那样写成编译期常量
+    async fn home_dir(session_id: &str) -> Result<String> {
+        let (_, stdout, _) = SshClient::execute_command(session_id, "echo -n $HOME").await?;
+        let home = stdout.trim().to_string();
+        if home.is_empty() {
+            anyhow::bail!("无法解析远程用户的 \\$HOME，无法定位用户级 systemd 单元目录");
+        }
+        Ok(home)
+    }
+}
+
+#[async_trait::async_trait]
+impl ServiceManager for SystemdUserManager {
+    async fn unit_exists(&self, session_id: &str, name: &str, _sudo_password: Option<&str>) -> Result<bool> {
+        let home = Self::home_dir(session_id).await?;
+        let cmd = format!(
+            "test -f '{}/.config/systemd/user/{}.service' && echo exists || echo not_exists",
+            home, name
+        );
+        let (_, stdout, _) = SshClient::execute_command(session_id, &cmd).await?;
+        Ok(stdout.trim() == "exists")
+    }
+
+    async fn is_active(&self, session_id: &str, name: &str, _sudo_password: Option<&str>) -> Result<bool> {
+        let cmd = format!("systemctl --user is-active {} 2>/dev/null", name);
+        let (_, stdout, _) = SshClient::execute_command(session_id, &cmd).await?;
+        Ok(stdout.trim() == "active")
+    }
+
+    async fn is_enabled(&self, session_id: &str, name: &str, _sudo_password: Option<&str>) -> Result<bool> {
+        let cmd = format!("systemctl --user is-enabled {} 2>/dev/null", name);
+        let (_, stdout, _) = SshClient::execute_command(session_id, &cmd).await?;
+        Ok(stdout.trim() == "enabled")
+    }
+
+    async fn stop(&self, session_id: &str, name: &str, _sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_command(session_id, &format!("systemctl --user stop {}", name)).await
+    }
+
+    async fn start(&self, session_id: &str, name: &str, _sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_command(session_id, &format!("systemctl --user start {}", name)).await
+    }
+
+    async fn restart(&self, session_id: &str, name: &str, _sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_command(session_id, &format!("systemctl --user restart {}", name)).await
+    }
+
+    async fn enable(&self, session_id: &str, name: &str, _sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_command(session_id, &format!("systemctl --user enable {}", name)).await
+    }
+
+    async fn disable(&self, session_id: &str, name: &str, _sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_command(session_id, &format!("systemctl --user disable {}", name)).await
+    }
+
+    async fn reload(&self, session_id: &str, name: &str, _sudo_password: Option<&str>) -> Result<(i32, String, String)> {
+        SshClient::execute_command(session_id, &format!("systemctl --user reload {}", name)).await
+    }
+
+    async fn status_summary(&self, session_id: &str, name: &str, _sudo_password: Option<&str>) -> Result<String> {
+        let cmd = format!("systemctl --user status {} --no-pager -l", name);
+        let (_, stdout, _) = SshClient::execute_command(session_id, &cmd).await?;
+        Ok(stdout)
+    }
+
+    async fn health_status(&self, session_id: &str, name: &str, sudo_password: Option<&str>) -> Result<ServiceStatus> {
+        let cmd = format!(
+            "systemctl --user show {} --property=ActiveState,SubState,MainPID,ExecMainStatus,Result,ActiveEnterTimestamp",
+            name
+        );
+        let (exit_status, stdout, _) = SshClient::execute_command(session_id, &cmd).await?;
+        if exit_status != 0 || stdout.trim().is_empty() {
+            let active = self.is_active(session_id, name, sudo_password).await.unwrap_or(false);
+            let raw = self.status_summary(session_id, name, sudo_password).await.ok();
+            return Ok(ServiceStatus::from_is_active(active, raw));
+        }
+        Ok(ServiceStatus::parse_systemctl_show(&stdout))
+    }
+
+    // unit 文件路径依赖运行时解析出的 $HOME，无法在这里同步给出；
+    // remove_unit 因此需要自己覆盖，而不能依赖默认实现
+
+    async fn remove_unit(&self, session_id: &str, name: &str, _sudo_password: Option<&str>) -> Result<()> {
+        let home = Self::home_dir(session_id).await?;
+        let cmd = format!(
+            "rm -f '{}/.config/systemd/user/{}.service' && systemctl --user daemon-reload",
+            home, name
+        );
+        let (exit_status, _, stderr) = SshClient::execute_command(session_id, &cmd).await?;
+        if exit_status != 0 {
+            anyhow::bail!("删除用户级服务定义文件失败: {}", stderr.trim());
+        }
+        Ok(())
+    }
+
+    async fn install_unit(
+        &self,
+        session_id: &str,
+        name: &str,
+        description: &str,
+        working_dir: &str,
+        exec_start: &str,
+        _user: Option<&str>,
+        unit: &UnitConfig,
+        _sudo_password: Option<&str>,
+    ) -> Result<()> {
+        // User= 对用户级实例无意义，不论调用方传入什么都忽略
+        let unit_content = build_systemd_unit(description, working_dir, exec_start, None, unit, true);
+
+        let home = Self::home_dir(session_id).await?;
+        let user_unit_dir = format!("{}/.config/systemd/user", home);
+        SshClient::execute_command(session_id, &format!("mkdir -p '{}'", user_unit_dir)).await?;
+
+        let temp_unit = format!("/tmp/{}.service", name);
+        std::fs::write(&temp_unit, unit_content)?;
+        SshClient::upload_file(session_id, &temp_unit, &temp_unit).await?;
+        let remote_path = format!("{}/{}.service", user_unit_dir, name);
+        let cmd = format!("mv '{}' '{}' && systemctl --user daemon-reload", temp_unit, remote_path);
+        let (exit_status, _, stderr) = SshClient::execute_command(session_id, &cmd).await?;
+        let _ = std::fs::remove_file(&temp_unit);
+        if exit_status != 0 {
+            anyhow::bail!("安装用户级 systemd 服务失败: {}", stderr.trim());
+        }
+
+        // 不开启 linger 的话，用户一断开 SSH 连接，systemd --user 实例就会被
+        // 回收，服务随之停止；这一步失败只记录警告，不影响安装本身是否成功
+        let (linger_status, _, linger_stderr) =
+            SshClient::execute_command(session_id, "loginctl enable-linger \"$(whoami)\" 2>&1").await?;
+        if linger_status != 0 {
+            eprintln!("[WARN] 开启 linger 失败，用户断开 SSH 后该服务可能随之停止: {}", linger_stderr.trim());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use super::log::DeployLog;
+use crate::ssh::SshClient;
+use serde::Deserialize;
+
+/// 驱动非标准初始化系统的命令模板。运维可以在远程主机的 `{install_dir}/system.toml`
+/// 里放一份覆盖表，给某个动作指定一条自定义命令（`{name}` 占位符会被替换成服务名）；
+/// 没有覆盖的动作仍然走探测出来的基础 ServiceManager（systemd/OpenRC/BSD）实现，
+/// 不需要把整份命令表都写全，也不需要认识这台主机具体是什么发行版。
+#[derive(Debug, Default, Deserialize)]
+pub struct SystemDescriptor {
+    #[serde(default)]
+    pub init: InitCommands,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct InitCommands {
+    pub boot: Option<String>,
+    pub stop: Option<String>,
+    pub restart: Option<String>,
+    pub reload: Option<String>,
+    pub enable: Option<String>,
+    pub disable: Option<String>,
+    pub is_active: Option<String>,
+    pub is_enabled: Option<String>,
+    pub status: Option<String>,
+}
+
+impl InitCommands {
+    /// 把模板里的 `{name}` 换成服务名；没有配置这个动作时返回 None，
+    /// 调用方据此决定是否回退到基础 ServiceManager 的实现
+    pub fn render(template: &Option<String>, name: &str) -> Option<String> {
+        template.as_ref().map(|t| t.replace("{name}", name))
+    }
+}
+
+/// 尝试从 `{install_dir}/system.toml` 读取描述符。文件不存在、读取失败或内容
+/// 解析失败都视为"没有描述符"，调用方据此完全退化为探测出来的基础实现；`log`
+/// 传入时解析失败会记一条说明，而不是只打到进程的stderr上
+pub async fn load(session_id: &str, install_dir: &str, log: Option<&DeployLog>) -> Option<SystemDescriptor> {
+    let path = format!("{}/system.toml", install_dir);
+    let cmd = format!("cat '{}' 2>/dev/null", path);
+    let (exit_status, stdout, _) = SshClient::execute_command(session_id, &cmd).await.ok()?;
+    if exit_status != 0 || stdout.trim().is_empty() {
+        return None;
+    }
+
+    match toml::from_str(&stdout) {
+        Ok(descriptor) => Some(descriptor),
+        Err(e) => {
+            if let Some(l) = log {
+                l.note(&format!("解析 {} 失败，忽略该描述符: {}", path, e));
+            }
+            None
+        }
+    }
+}
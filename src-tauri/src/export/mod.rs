@@ -3,9 +3,12 @@ use csv::Writer;
 use serde::Deserialize;
 use serde_json::Value;
 use chrono::{Utc, TimeZone, FixedOffset};
-use std::collections::HashMap;
-use std::path::Path;
-use std::sync::OnceLock;
+use std::collections::{HashMap, HashSet};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+mod parquet_export;
 
 // 配置结构体
 #[derive(Debug, Deserialize, Clone)]
@@ -15,10 +18,97 @@ struct ExportConfig {
     extract_from_payload: HashMap<String, Vec<String>>,
     #[serde(default)]
     field_name_mapping: HashMap<String, String>,
+    /// 字段名 -> 声明类型（"string"/"number"/"boolean"），CSV回读时按声明类型解析，
+    /// 避免把纯数字的设备序列号、带前导零的ID当成数字误转换。未声明的列仍然退化
+    /// 成原有的猜测式解析（先试整数再试浮点数，都不行就当字符串）
+    #[serde(default)]
+    column_types: HashMap<String, String>,
+    /// CSV写出方言（分隔符/引号风格/是否写BOM），不影响Json/Ndjson/Parquet
+    #[serde(default)]
+    csv_dialect: CsvDialect,
+}
+
+/// CSV写出方言，对应 `csv::WriterBuilder` 上可配的那几项。默认是逗号分隔、
+/// 仅在必要时加引号、带UTF-8 BOM——跟改造前硬编码的行为完全一致。有些地区的
+/// Excel把逗号当小数点用、要求用分号分隔，或者有下游工具不认BOM，这些都通过
+/// `csv_export_config.toml` 里的 `[csv_dialect]` 小节覆盖，不需要改代码
+#[derive(Debug, Deserialize, Clone)]
+struct CsvDialect {
+    #[serde(default = "default_delimiter")]
+    delimiter: char,
+    #[serde(default)]
+    quote_style: CsvQuoteStyle,
+    #[serde(default = "default_write_bom")]
+    write_bom: bool,
+}
+
+impl Default for CsvDialect {
+    fn default() -> Self {
+        CsvDialect {
+            delimiter: default_delimiter(),
+            quote_style: CsvQuoteStyle::default(),
+            write_bom: default_write_bom(),
+        }
+    }
+}
+
+impl CsvDialect {
+    fn delimiter_byte(&self) -> Result<u8, String> {
+        if self.delimiter.is_ascii() {
+            Ok(self.delimiter as u8)
+        } else {
+            Err(format!("CSV分隔符必须是单个ASCII字符，收到: {}", self.delimiter))
+        }
+    }
 }
 
-// 全局配置缓存
-static CONFIG: OnceLock<ExportConfig> = OnceLock::new();
+fn default_delimiter() -> char {
+    ','
+}
+
+fn default_write_bom() -> bool {
+    true
+}
+
+/// 引号风格，对应 `csv::QuoteStyle`。`Always`（即"quote_all"模式）把每个字段都
+/// 包进引号里，类似RPKI输出工具里"compat CSV"那种尽量贴近下游老工具期望格式的
+/// 变体；这里没有AS号这类需要特殊豁免的字段类型，所以`Always`就是对所有列生效，
+/// 不单独区分数值列
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+enum CsvQuoteStyle {
+    #[default]
+    Necessary,
+    Always,
+    NonNumeric,
+    Never,
+}
+
+impl CsvQuoteStyle {
+    fn to_csv_quote_style(self) -> csv::QuoteStyle {
+        match self {
+            CsvQuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+            CsvQuoteStyle::Always => csv::QuoteStyle::Always,
+            CsvQuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+            CsvQuoteStyle::Never => csv::QuoteStyle::Never,
+        }
+    }
+}
+
+// 当前生效的配置，连同解析出来的文件路径（默认配置时为None）一起缓存，
+// 这样 `reload_config` 不传路径时知道该重新读哪个文件
+struct ConfigState {
+    config: ExportConfig,
+    source_path: Option<PathBuf>,
+}
+
+// 全局配置缓存。用RwLock而不是单纯的OnceLock是因为配置现在可以在进程运行期间
+// 被 `reload_config` 换掉，不再是"只初始化一次就不变"
+static CONFIG: OnceLock<RwLock<ConfigState>> = OnceLock::new();
+
+fn config_cell() -> &'static RwLock<ConfigState> {
+    CONFIG.get_or_init(|| RwLock::new(resolve_config(None)))
+}
 
 // 默认配置
 fn default_config() -> ExportConfig {
@@ -34,39 +124,90 @@ fn default_config() -> ExportConfig {
         ],
         extract_from_payload: HashMap::new(),
         field_name_mapping: HashMap::new(),
+        column_types: HashMap::from([("device_sn".to_string(), "string".to_string())]),
+        csv_dialect: CsvDialect::default(),
     }
 }
 
-// 加载配置文件
-fn load_config() -> ExportConfig {
-    CONFIG.get_or_init(|| {
-        // 1. 优先从可执行文件同目录读取
-        if let Ok(exe_path) = std::env::current_exe() {
-            if let Some(exe_dir) = exe_path.parent() {
-                let config_path = exe_dir.join("csv_export_config.toml");
-                if config_path.exists() {
-                    if let Ok(config) = parse_config_file(&config_path) {
-                        return config;
-                    }
+// 按固定搜索顺序解析配置文件：可执行文件同目录 -> 项目根目录 -> 默认配置。
+// 不接受失败——任何一步读取/解析不出来就退到下一步，最终总能拿到一份可用的配置，
+// 沿用原来 `load_config` 的容错行为
+fn resolve_config(explicit_path: Option<&Path>) -> ConfigState {
+    if let Some(path) = explicit_path {
+        if let Ok(config) = parse_config_file(path) {
+            return ConfigState { config, source_path: Some(path.to_path_buf()) };
+        }
+        return ConfigState { config: default_config(), source_path: None };
+    }
+
+    // 1. 优先从可执行文件同目录读取
+    if let Ok(exe_path) = std::env::current_exe() {
+        if let Some(exe_dir) = exe_path.parent() {
+            let config_path = exe_dir.join("csv_export_config.toml");
+            if config_path.exists() {
+                if let Ok(config) = parse_config_file(&config_path) {
+                    return ConfigState { config, source_path: Some(config_path) };
                 }
             }
         }
-        
-        // 2. 从项目根目录读取（CARGO_MANIFEST_DIR 是 src-tauri，父目录是项目根目录）
-        let project_root = Path::new(env!("CARGO_MANIFEST_DIR"));
-        let config_path = project_root.parent()
-            .map(|p| p.join("csv_export_config.toml"))
-            .unwrap_or_else(|| project_root.join("csv_export_config.toml"));
-        
-        if config_path.exists() {
-            if let Ok(config) = parse_config_file(&config_path) {
-                return config;
-            }
+    }
+
+    // 2. 从项目根目录读取（CARGO_MANIFEST_DIR 是 src-tauri，父目录是项目根目录）
+    let project_root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let config_path = project_root.parent()
+        .map(|p| p.join("csv_export_config.toml"))
+        .unwrap_or_else(|| project_root.join("csv_export_config.toml"));
+
+    if config_path.exists() {
+        if let Ok(config) = parse_config_file(&config_path) {
+            return ConfigState { config, source_path: Some(config_path) };
         }
-        
-        // 3. 使用默认配置
-        default_config()
-    }).clone()
+    }
+
+    // 3. 使用默认配置
+    ConfigState { config: default_config(), source_path: None }
+}
+
+// 显式指定路径时解析失败要报错，而不是像 `resolve_config` 那样静默退回默认配置——
+// 调用方（`reload_config`/导出时传入的 `config_path`）明确点了这份文件，读不出来
+// 应该让用户知道，而不是悄悄换成一份看起来"能用"但其实不对的配置
+fn resolve_explicit_config(path: &Path) -> Result<ExportConfig, String> {
+    parse_config_file(path).map_err(|e| format!("Failed to load config from {}: {}", path.display(), e))
+}
+
+// 读当前缓存的配置
+fn load_config() -> ExportConfig {
+    config_cell().read().unwrap().config.clone()
+}
+
+// 导出时如果传了显式config_path，就直接按这个路径解析（不经过缓存、也不影响
+// 热重载后的全局状态），否则退回当前缓存的配置
+fn load_config_with_override(explicit_path: Option<&str>) -> Result<ExportConfig, String> {
+    match explicit_path {
+        Some(p) => resolve_explicit_config(Path::new(p)),
+        None => Ok(load_config()),
+    }
+}
+
+/// 重新加载导出配置，替换掉进程里缓存的那一份，不需要重启应用就能看到
+/// `csv_export_config.toml` 的最新内容。传了 `config_path` 就固定从这个路径读取；
+/// 不传时优先重新读取当前缓存记住的那份文件来源，只有当前还是默认配置（从没
+/// 命中过任何文件）才会重新走一遍搜索顺序。读取/解析失败时返回错误且缓存保持不变
+pub fn reload_config(config_path: Option<String>) -> Result<(), String> {
+    let path_to_reload = config_path
+        .map(PathBuf::from)
+        .or_else(|| config_cell().read().unwrap().source_path.clone());
+
+    let new_state = match path_to_reload {
+        Some(path) => {
+            let config = resolve_explicit_config(&path)?;
+            ConfigState { config, source_path: Some(path) }
+        }
+        None => resolve_config(None),
+    };
+
+    *config_cell().write().unwrap() = new_state;
+    Ok(())
 }
 
 // 解析配置文件
@@ -112,472 +253,487 @@ fn format_value(value: Option<&Value>) -> String {
     }
 }
 
-// 过滤字段并提取payload_json中的字段
-fn filter_and_extract_fields(data: &[Value], config: &ExportConfig) -> Vec<HashMap<String, Value>> {
-    if data.is_empty() {
-        return Vec::new();
-    }
-    
+// 单行：过滤主表字段 + 提取payload_json中配置的字段。之前这一步是按整个 `Vec<Value>`
+// 批量处理的，现在改成单行处理，配合 export() / export_from_csv_file 的两遍扫描
+// （先只扫列名、再边处理边写）实现同一时刻只有一行数据在内存里
+fn filter_and_extract_row(row: &Value, config: &ExportConfig) -> Option<HashMap<String, Value>> {
+    let obj = row.as_object()?;
+
     let main_table_fields = &config.main_table_fields;
     let extract_config = &config.extract_from_payload;
     let field_mapping = &config.field_name_mapping;
-    
-    let mut result = Vec::new();
-    
-    for row in data {
-        if let Some(obj) = row.as_object() {
-            let mut new_row = HashMap::new();
-            // 获取设备类型，转换为大写以匹配配置（配置中的设备类型通常是大写）
-            let device_type = obj.get("device_type")
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_uppercase())
-                .unwrap_or_else(|| "default".to_string());
-            
-            // 1. 保留主表字段
-            for field in main_table_fields {
-                if let Some(value) = obj.get(field) {
-                    new_row.insert(field.clone(), value.clone());
+
+    let mut new_row = HashMap::new();
+    // 获取设备类型，转换为大写以匹配配置（配置中的设备类型通常是大写）
+    let device_type = obj.get("device_type")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_uppercase())
+        .unwrap_or_else(|| "default".to_string());
+
+    // 1. 保留主表字段
+    for field in main_table_fields {
+        if let Some(value) = obj.get(field) {
+            new_row.insert(field.clone(), value.clone());
+        }
+    }
+
+    // 2. 从payload_json中提取配置的字段
+    if let Some(payload_json) = obj.get("payload_json") {
+        // 跳过空值和空字符串
+        if !payload_json.is_null() {
+            let payload_data: Option<HashMap<String, Value>> = match payload_json {
+                Value::String(s) => {
+                    // 跳过空字符串
+                    if s.is_empty() {
+                        None
+                    } else {
+                        serde_json::from_str(s).ok()
+                    }
                 }
-            }
-            
-            // 2. 从payload_json中提取配置的字段
-            if let Some(payload_json) = obj.get("payload_json") {
-                // 跳过空值和空字符串
-                if !payload_json.is_null() {
-                    let payload_data: Option<HashMap<String, Value>> = match payload_json {
-                        Value::String(s) => {
-                            // 跳过空字符串
-                            if s.is_empty() {
-                                None
-                            } else {
-                                serde_json::from_str(s).ok()
-                            }
-                        }
-                        Value::Object(o) => {
-                            if o.is_empty() {
-                                None
-                            } else {
-                                Some(o.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
-                            }
-                        }
-                        _ => None,
-                    };
-                    
-                    if let Some(payload) = payload_data {
-                        // 获取该设备类型需要提取的字段列表
-                        // 先尝试精确匹配，再尝试默认配置
-                        let fields_to_extract = extract_config.get(&device_type)
-                            .or_else(|| extract_config.get("default"))
-                            .cloned()
-                            .unwrap_or_default();
-                        
-                        // 如果配置了该设备类型的字段，进行提取
-                        if !fields_to_extract.is_empty() {
-                            // 提取字段
-                            for field_key in fields_to_extract {
-                                if let Some(value) = payload.get(&field_key) {
-                                    // 跳过null值
-                                    if !value.is_null() {
-                                        // 应用字段名映射
-                                        let output_field_name = field_mapping.get(&field_key)
-                                            .cloned()
-                                            .unwrap_or(field_key.clone());
-                                        new_row.insert(output_field_name, value.clone());
-                                    }
-                                }
+                Value::Object(o) => {
+                    if o.is_empty() {
+                        None
+                    } else {
+                        Some(o.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+                    }
+                }
+                _ => None,
+            };
+
+            if let Some(payload) = payload_data {
+                // 获取该设备类型需要提取的字段列表
+                // 先尝试精确匹配，再尝试默认配置
+                let fields_to_extract = extract_config.get(&device_type)
+                    .or_else(|| extract_config.get("default"))
+                    .cloned()
+                    .unwrap_or_default();
+
+                // 如果配置了该设备类型的字段，进行提取
+                if !fields_to_extract.is_empty() {
+                    // 提取字段
+                    for field_key in fields_to_extract {
+                        if let Some(value) = payload.get(&field_key) {
+                            // 跳过null值
+                            if !value.is_null() {
+                                // 应用字段名映射
+                                let output_field_name = field_mapping.get(&field_key)
+                                    .cloned()
+                                    .unwrap_or(field_key.clone());
+                                new_row.insert(output_field_name, value.clone());
                             }
                         }
                     }
                 }
             }
-            
-            result.push(new_row);
         }
     }
-    
-    result
+
+    Some(new_row)
 }
 
-// 为数据添加格式化时间戳
-fn add_formatted_timestamps(data: Vec<HashMap<String, Value>>) -> Vec<HashMap<String, Value>> {
-    data.into_iter().map(|mut row| {
-        // 格式化timestamp（秒级）
-        if let Some(timestamp) = row.get("timestamp") {
-            if let Some(ts) = timestamp.as_i64() {
-                row.insert("timestamp".to_string(), Value::String(format_timestamp(ts, false)));
-            }
+// 单行：格式化timestamp（秒级）/local_timestamp（毫秒级）
+fn format_timestamps_row(mut row: HashMap<String, Value>) -> HashMap<String, Value> {
+    if let Some(timestamp) = row.get("timestamp") {
+        if let Some(ts) = timestamp.as_i64() {
+            row.insert("timestamp".to_string(), Value::String(format_timestamp(ts, false)));
         }
-        
-        // 格式化local_timestamp（毫秒级）
-        if let Some(local_timestamp) = row.get("local_timestamp") {
-            if let Some(ts) = local_timestamp.as_i64() {
-                row.insert("local_timestamp".to_string(), Value::String(format_timestamp(ts, true)));
-            }
+    }
+
+    if let Some(local_timestamp) = row.get("local_timestamp") {
+        if let Some(ts) = local_timestamp.as_i64() {
+            row.insert("local_timestamp".to_string(), Value::String(format_timestamp(ts, true)));
         }
-        
-        row
-    }).collect()
+    }
+
+    row
 }
 
-// 重新排列列顺序（普通查询）
-fn reorder_columns(data: Vec<HashMap<String, Value>>) -> Vec<HashMap<String, Value>> {
-    if data.is_empty() {
-        return data;
-    }
-    
-    let priority_columns = vec![
-        "id".to_string(),
-        "device_sn".to_string(),
-        "device_type".to_string(),
-        "timestamp".to_string(),
-        "local_timestamp".to_string(),
-    ];
-    
-    data.into_iter().map(|row| {
-        let mut new_row = HashMap::new();
-        
-        // 先添加优先级列
-        for col in &priority_columns {
-            if let Some(value) = row.get(col) {
-                new_row.insert(col.clone(), value.clone());
-            }
+// 准备普通查询的一行数据用于导出：过滤字段 + 提取payload + 格式化时间戳。
+// 原来还有一步"重新排列列顺序"，但最终写出（CSV/JSON/Ndjson）都是按预先算好的
+// `fieldnames` 顺序去取值，HashMap本身不保证迭代顺序，那一步对输出没有任何
+// 实际影响，这里不再重复做
+fn process_normal_row(row: &Value, config: &ExportConfig) -> Option<HashMap<String, Value>> {
+    filter_and_extract_row(row, config).map(format_timestamps_row)
+}
+
+// 准备宽表查询的一行数据用于导出
+fn process_wide_table_row(row: &Value) -> Option<HashMap<String, Value>> {
+    let obj = row.as_object()?;
+    let new_row: HashMap<String, Value> = obj.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    Some(format_timestamps_row(new_row))
+}
+
+// 根据扫描到的所有列名算出最终的列顺序：宽表查询local_timestamp优先、其余按
+// 字母顺序；普通查询主表字段（按配置顺序，只保留实际出现过的）+ 扩展字段
+// （按字母顺序）
+fn compute_fieldnames(all_fieldnames: HashSet<String>, is_wide_table: bool, config: &ExportConfig) -> Vec<String> {
+    if is_wide_table {
+        let mut fieldnames: Vec<String> = all_fieldnames.into_iter().collect();
+        fieldnames.sort();
+
+        if let Some(pos) = fieldnames.iter().position(|x| x == "local_timestamp") {
+            fieldnames.remove(pos);
+            fieldnames.insert(0, "local_timestamp".to_string());
         }
-        
-        // 再添加其他列
-        for (key, value) in row {
-            if !priority_columns.contains(&key) {
-                new_row.insert(key, value);
+
+        fieldnames
+    } else {
+        let main_fields = &config.main_table_fields;
+        let mut fieldnames: Vec<String> = Vec::new();
+
+        for field in main_fields {
+            if all_fieldnames.contains(field) {
+                fieldnames.push(field.clone());
             }
         }
-        
-        new_row
-    }).collect()
+
+        let mut ext_fields: Vec<String> = all_fieldnames.into_iter()
+            .filter(|f| !main_fields.contains(f))
+            .collect();
+        ext_fields.sort();
+        fieldnames.extend(ext_fields);
+
+        fieldnames
+    }
 }
 
-// 准备普通查询数据用于导出
-fn prepare_for_export(data: &[Value], config: &ExportConfig) -> Vec<HashMap<String, Value>> {
-    // 1. 过滤字段，提取payload_json中的字段
-    let filtered = filter_and_extract_fields(data, config);
-    
-    // 2. 格式化时间戳
-    let formatted = add_formatted_timestamps(filtered);
-    
-    // 3. 重新排列列顺序
-    reorder_columns(formatted)
+/// 导出目标格式。`Csv` 是原有行为，`Json`/`Ndjson`/`Parquet` 复用同一套按行处理
+/// （`process_normal_row`/`process_wide_table_row`）+ 统一算列顺序
+/// （`compute_fieldnames`）的流水线，只有最后落盘这一步不一样：`Json` 按
+/// `fieldnames` 顺序写成一个JSON数组，`Ndjson` 每行一个JSON对象，`Parquet` 按
+/// [`parquet_export::write_parquet`] 推断出的列类型写成单个row group的列式文件，
+/// 体积更小、重新查询也更快，适合设备历史数据的大体量导出
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Csv,
+    Json,
+    Ndjson,
+    Parquet,
 }
 
-// 准备宽表查询数据用于导出
-fn prepare_wide_table_for_export(data: &[Value]) -> Vec<HashMap<String, Value>> {
-    if data.is_empty() {
-        return Vec::new();
+impl OutputFormat {
+    fn parse(format: Option<&str>) -> Self {
+        match format {
+            Some("json") => OutputFormat::Json,
+            Some("ndjson") => OutputFormat::Ndjson,
+            Some("parquet") => OutputFormat::Parquet,
+            _ => OutputFormat::Csv,
+        }
     }
-    
-    let mut result = Vec::new();
-    
-    for row in data {
-        if let Some(obj) = row.as_object() {
-            let mut new_row: HashMap<String, Value> = obj.iter()
-                .map(|(k, v)| (k.clone(), v.clone()))
-                .collect();
-            
-            // 格式化local_timestamp（毫秒级）
-            if let Some(local_timestamp) = new_row.get("local_timestamp") {
-                if let Some(ts) = local_timestamp.as_i64() {
-                    new_row.insert("local_timestamp".to_string(), Value::String(format_timestamp(ts, true)));
+}
+
+/// CSV/Json/Ndjson三种格式共用的行级写入器：每种格式内部维护自己的底层writer，
+/// 对外暴露统一的 `push_row`/`finish` 接口，调用方一次只处理一行就写一行，不需要
+/// 先攒出一份完整的 `Vec<HashMap<..>>`。Parquet不走这里——列式写入天然需要按列
+/// 缓冲，而且列类型推断要扫一遍所有值，没办法只靠列名就定下来，见 `export()` 里
+/// 单独的Parquet分支
+enum RowSink {
+    Csv(Writer<std::fs::File>),
+    Json { file: std::fs::File, wrote_first_row: bool },
+    Ndjson(std::fs::File),
+}
+
+impl RowSink {
+    fn open(format: OutputFormat, file_path: &str, fieldnames: &[String], csv_dialect: &CsvDialect) -> Result<Self, String> {
+        match format {
+            OutputFormat::Csv => {
+                let mut file = std::fs::File::create(file_path)
+                    .map_err(|e| format!("Failed to create CSV file: {}", e))?;
+                if csv_dialect.write_bom {
+                    // 写入UTF-8 BOM，让Excel正确识别编码；部分下游工具不认BOM，
+                    // 所以这个可以通过配置关掉
+                    file.write_all(&[0xEF, 0xBB, 0xBF])
+                        .map_err(|e| format!("Failed to write BOM: {}", e))?;
                 }
+
+                let mut writer = csv::WriterBuilder::new()
+                    .delimiter(csv_dialect.delimiter_byte()?)
+                    .quote_style(csv_dialect.quote_style.to_csv_quote_style())
+                    .from_writer(file);
+                writer.write_record(fieldnames)
+                    .map_err(|e| format!("Failed to write header: {}", e))?;
+                Ok(Self::Csv(writer))
+            }
+            OutputFormat::Json => {
+                let mut file = std::fs::File::create(file_path)
+                    .map_err(|e| format!("Failed to create output file: {}", e))?;
+                file.write_all(b"[").map_err(|e| format!("Failed to write JSON file: {}", e))?;
+                Ok(Self::Json { file, wrote_first_row: false })
             }
-            
-            result.push(new_row);
+            OutputFormat::Ndjson => {
+                let file = std::fs::File::create(file_path)
+                    .map_err(|e| format!("Failed to create output file: {}", e))?;
+                Ok(Self::Ndjson(file))
+            }
+            OutputFormat::Parquet => unreachable!("Parquet走export()里独立的列式批量路径，不经过RowSink"),
         }
     }
-    
-    // 重新排列列顺序：local_timestamp优先，其他列按字母顺序
-    result.into_iter().map(|row| {
-        let mut new_row = HashMap::new();
-        
-        // 先添加local_timestamp（如果存在）
-        if let Some(value) = row.get("local_timestamp") {
-            new_row.insert("local_timestamp".to_string(), value.clone());
-        }
-        
-        // 再添加其他列（按字母顺序）
-        let mut other_keys: Vec<String> = row.keys()
-            .filter(|k| *k != "local_timestamp")
-            .cloned()
-            .collect();
-        other_keys.sort();
-        
-        for key in other_keys {
-            if let Some(value) = row.get(&key) {
-                new_row.insert(key, value.clone());
+
+    fn push_row(&mut self, fieldnames: &[String], row: &HashMap<String, Value>) -> Result<(), String> {
+        match self {
+            Self::Csv(writer) => {
+                let record: Vec<String> = fieldnames.iter().map(|col| format_value(row.get(col))).collect();
+                writer.write_record(&record).map_err(|e| format!("Failed to write record: {}", e))
+            }
+            Self::Json { file, wrote_first_row } => {
+                if *wrote_first_row {
+                    file.write_all(b",").map_err(|e| format!("Failed to write JSON file: {}", e))?;
+                }
+                *wrote_first_row = true;
+
+                let mut obj = serde_json::Map::new();
+                for col in fieldnames {
+                    obj.insert(col.clone(), row.get(col).cloned().unwrap_or(Value::Null));
+                }
+                let text = serde_json::to_string(&Value::Object(obj))
+                    .map_err(|e| format!("Failed to serialize row: {}", e))?;
+                file.write_all(text.as_bytes()).map_err(|e| format!("Failed to write JSON file: {}", e))
             }
+            Self::Ndjson(file) => {
+                let mut obj = serde_json::Map::new();
+                for col in fieldnames {
+                    obj.insert(col.clone(), row.get(col).cloned().unwrap_or(Value::Null));
+                }
+                let line = serde_json::to_string(&obj).map_err(|e| format!("Failed to serialize row: {}", e))?;
+                writeln!(file, "{}", line).map_err(|e| format!("Failed to write row: {}", e))
+            }
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self {
+            Self::Csv(mut writer) => writer.flush().map_err(|e| format!("Failed to flush CSV file: {}", e)),
+            Self::Json { mut file, .. } => file.write_all(b"]").map_err(|e| format!("Failed to write JSON file: {}", e)),
+            Self::Ndjson(mut file) => file.flush().map_err(|e| format!("Failed to flush output file: {}", e)),
         }
-        
-        new_row
-    }).collect()
+    }
 }
 
-// 主导出函数
-pub async fn export_to_csv(
+// 主导出函数：`format` 不传时退化成 `Csv`（原有行为）。CSV/Json/Ndjson都走两遍
+// 扫描：第一遍只保留处理后每行的列名（算 `fieldnames`），处理完立刻丢弃整行的值；
+// 第二遍重新按行处理、边处理边通过 `RowSink` 写出，同一时刻只有一行数据在内存里，
+// 不再像之前那样把所有行先攒成一份 `Vec<HashMap<..>>`
+pub async fn export(
     data: Value,
     file_path: String,
     query_type: Option<String>,
+    format: Option<String>,
+    config_path: Option<String>,
 ) -> Result<(), String> {
-    // 优先使用CSV文件路径（如果存在）
-    if let Some(csv_file_path) = data.get("csvFilePath")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string())
-    {
-        // 如果CSV文件存在，直接复制并处理
-        if std::path::Path::new(&csv_file_path).exists() {
-            return export_from_csv_file(&csv_file_path, &file_path, query_type.as_deref()).await;
+    let output_format = OutputFormat::parse(format.as_deref());
+
+    // csvFilePath这条快速路径本来就是跳过行处理流水线直接复制已经写好的CSV文本
+    // 内容，只对Csv格式有意义；Json/Ndjson/Parquet需要重新过一遍结构化数据，统一
+    // 走下面从`rows`字段导出的路径
+    if output_format == OutputFormat::Csv {
+        if let Some(csv_file_path) = data.get("csvFilePath")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+        {
+            // 如果CSV文件存在，直接复制并处理
+            if std::path::Path::new(&csv_file_path).exists() {
+                return export_from_csv_file(&csv_file_path, &file_path, query_type.as_deref(), config_path.as_deref()).await;
+            }
         }
     }
-    
+
     // 回退到从JSON数据导出
     let rows = data
         .get("rows")
         .and_then(|v| v.as_array())
         .ok_or("Invalid data format")?;
-    
+
     if rows.is_empty() {
         return Err("No data to export".to_string());
     }
-    
-    // 根据查询类型选择处理方式
-    let processed_data = match query_type.as_deref() {
-        Some("wide_table") => {
-            prepare_wide_table_for_export(rows)
-        }
-        _ => {
-            let config = load_config();
-            prepare_for_export(rows, &config)
+
+    let is_wide_table = query_type.as_deref() == Some("wide_table");
+    let config = load_config_with_override(config_path.as_deref())?;
+    let process_row = |row: &Value| -> Option<HashMap<String, Value>> {
+        if is_wide_table {
+            process_wide_table_row(row)
+        } else {
+            process_normal_row(row, &config)
         }
     };
-    
-    if processed_data.is_empty() {
+
+    // 第一遍：只扫描处理后每行的列名
+    let mut all_fieldnames = HashSet::new();
+    let mut any_row = false;
+    for row in rows {
+        if let Some(processed) = process_row(row) {
+            any_row = true;
+            all_fieldnames.extend(processed.into_keys());
+        }
+    }
+
+    if !any_row {
         return Err("No data to export after processing".to_string());
     }
-    
-    // 获取列名顺序
-    // 对于普通查询，使用主表字段（按配置顺序）+ 扩展表字段（按字母顺序）
-    let fieldnames: Vec<String> = if query_type.as_deref() == Some("wide_table") {
-        // 宽表查询：使用local_timestamp优先，其他按字母顺序
-        let mut all_fieldnames = std::collections::HashSet::new();
-        for row in &processed_data {
-            all_fieldnames.extend(row.keys().cloned());
-        }
-        
-        let mut fieldnames: Vec<String> = all_fieldnames.into_iter().collect();
-        fieldnames.sort();
-        
-        // local_timestamp优先
-        if let Some(pos) = fieldnames.iter().position(|x| x == "local_timestamp") {
-            fieldnames.remove(pos);
-            fieldnames.insert(0, "local_timestamp".to_string());
-        }
-        
-        fieldnames
-    } else {
-        // 普通查询：主表字段（按配置顺序）+ 扩展表字段（按字母顺序）
-        let config = load_config();
-        let main_fields = &config.main_table_fields;
-        
-        let mut all_fieldnames = std::collections::HashSet::new();
-        for row in &processed_data {
-            all_fieldnames.extend(row.keys().cloned());
+
+    let fieldnames = compute_fieldnames(all_fieldnames, is_wide_table, &config);
+
+    // Parquet是列式格式，天然需要按列缓冲，而且列类型推断要看实际值（不只是列名），
+    // 没办法复用上面"只处理一行就丢"的两遍扫描，这里单独走仍然整批在内存里的路径
+    if output_format == OutputFormat::Parquet {
+        let processed_data: Vec<HashMap<String, Value>> = rows.iter().filter_map(&process_row).collect();
+        return parquet_export::write_parquet(&processed_data, &fieldnames, &file_path);
+    }
+
+    // 第二遍：重新逐行处理，处理完立刻写出，不保留
+    let mut sink = RowSink::open(output_format, &file_path, &fieldnames, &config.csv_dialect)?;
+    for row in rows {
+        if let Some(processed) = process_row(row) {
+            sink.push_row(&fieldnames, &processed)?;
         }
-        
-        let mut fieldnames: Vec<String> = Vec::new();
-        
-        // 1. 添加主表字段（按配置顺序，只包含实际存在的字段）
-        for field in main_fields {
-            if all_fieldnames.contains(field) {
-                fieldnames.push(field.clone());
+    }
+    sink.finish()
+}
+
+// 解析表头里内联的类型标注，形如 `activePower:number`；没有标注就原样返回表头名
+fn parse_header_annotation(header: &str) -> (String, Option<String>) {
+    match header.split_once(':') {
+        Some((name, ty)) => (name.to_string(), Some(ty.to_string())),
+        None => (header.to_string(), None),
+    }
+}
+
+// 按声明类型（内联表头标注优先于 `column_types` 配置）解析一个CSV字段值；
+// 未声明类型的列退化成原有的猜测式解析
+fn parse_field_value(field: &str, declared_type: Option<&str>) -> Value {
+    if field.is_empty() {
+        return Value::Null;
+    }
+
+    match declared_type {
+        Some("string") => Value::String(field.to_string()),
+        Some("number") => field
+            .parse::<i64>()
+            .map(|i| Value::Number(i.into()))
+            .unwrap_or_else(|_| {
+                field
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .map(Value::Number)
+                    .unwrap_or_else(|| Value::String(field.to_string()))
+            }),
+        Some("boolean") => match field.to_lowercase().as_str() {
+            "true" | "1" => Value::Bool(true),
+            "false" | "0" => Value::Bool(false),
+            _ => Value::String(field.to_string()),
+        },
+        _ => {
+            // 未声明类型：沿用原有的猜测式解析（先试整数再试浮点数）
+            if let Ok(int_val) = field.parse::<i64>() {
+                Value::Number(int_val.into())
+            } else if let Ok(float_val) = field.parse::<f64>() {
+                Value::Number(
+                    serde_json::Number::from_f64(float_val)
+                        .unwrap_or_else(|| serde_json::Number::from(0))
+                )
+            } else {
+                Value::String(field.to_string())
             }
         }
-        
-        // 2. 添加扩展表字段（按字母顺序）
-        let mut ext_fields: Vec<String> = all_fieldnames.into_iter()
-            .filter(|f| !main_fields.contains(f))
-            .collect();
-        ext_fields.sort();
-        fieldnames.extend(ext_fields);
-        
-        fieldnames
-    };
-    
-    // 创建CSV写入器（使用UTF-8 BOM编码）
-    use std::io::Write;
-    let mut file = std::fs::File::create(&file_path)
-        .map_err(|e| format!("Failed to create CSV file: {}", e))?;
-    
-    // 写入UTF-8 BOM
-    file.write_all(&[0xEF, 0xBB, 0xBF])
-        .map_err(|e| format!("Failed to write BOM: {}", e))?;
-    
-    // 创建CSV写入器（追加模式，因为BOM已经写入）
-    let mut wtr = Writer::from_writer(file);
-    
-    // 写入表头
-    wtr.write_record(&fieldnames)
-        .map_err(|e| format!("Failed to write header: {}", e))?;
-    
-    // 写入数据
-    for row in &processed_data {
-        let record: Vec<String> = fieldnames
-            .iter()
-            .map(|col| {
-                let value = row.get(col);
-                format_value(value)
-            })
-            .collect();
-        
-        wtr.write_record(&record)
-            .map_err(|e| format!("Failed to write record: {}", e))?;
     }
-    
-    wtr.flush()
-        .map_err(|e| format!("Failed to flush CSV file: {}", e))?;
-    
-    Ok(())
 }
 
-// 从CSV文件直接导出（处理payload_json字段提取）
+// 从CSV文件直接导出（处理payload_json字段提取）。源文件整份mmap进来，两遍扫描
+// （算列名、边处理边写）都直接对同一份映射内存操作，不需要`csv::Reader::from_path`
+// 重新打开文件，也不需要像之前那样把所有记录先收集成 `Vec<Value>`
 async fn export_from_csv_file(
     csv_file_path: &str,
     output_path: &str,
     query_type: Option<&str>,
+    config_path: Option<&str>,
 ) -> Result<(), String> {
-    use std::io::Write;
-    
-    // 读取CSV文件并解析
-    let mut reader = csv::Reader::from_path(csv_file_path)
+    let file = std::fs::File::open(csv_file_path)
         .map_err(|e| format!("Failed to read CSV file: {}", e))?;
-    
-    let headers = reader.headers()
+    let mmap = unsafe { memmap2::Mmap::map(&file) }
+        .map_err(|e| format!("Failed to mmap CSV file: {}", e))?;
+
+    let mut header_reader = csv::Reader::from_reader(&mmap[..]);
+    let raw_headers = header_reader.headers()
         .map_err(|e| format!("Failed to read CSV headers: {}", e))?
         .clone();
-    
-    // 检查是否包含payload_json字段
-    let has_payload_json = headers.iter().any(|h| h == "payload_json");
-    
+
+    // 拆出表头里内联的类型标注（如 `activePower:number`），后面按清洗过的字段名
+    // 做所有字段名相关的比较和查找
+    let headers: Vec<(String, Option<String>)> = raw_headers.iter().map(parse_header_annotation).collect();
+    let has_payload_json = headers.iter().any(|(name, _)| name == "payload_json");
+
     // 如果包含payload_json且不是wide_table查询，需要处理扩展表字段
-    if has_payload_json && query_type.as_deref() != Some("wide_table") {
-        // 加载配置
-        let config = load_config();
-        
-        // 读取所有行并转换为JSON格式
-        let mut rows = Vec::new();
-        for result in reader.records() {
-            let record = result.map_err(|e| format!("Failed to read CSV record: {}", e))?;
+    if has_payload_json && query_type != Some("wide_table") {
+        let config = load_config_with_override(config_path)?;
+
+        let row_from_record = |record: &csv::StringRecord| -> Value {
             let mut row_obj = serde_json::Map::new();
-            
             for (i, field) in record.iter().enumerate() {
-                if let Some(header) = headers.get(i) {
-                    let value: Value = if field.is_empty() {
-                        Value::Null
-                    } else {
-                        // 尝试转换为数字
-                        if let Ok(int_val) = field.parse::<i64>() {
-                            Value::Number(int_val.into())
-                        } else if let Ok(float_val) = field.parse::<f64>() {
-                            Value::Number(
-                                serde_json::Number::from_f64(float_val)
-                                    .unwrap_or_else(|| serde_json::Number::from(0))
-                            )
-                        } else {
-                            Value::String(field.to_string())
-                        }
-                    };
-                    row_obj.insert(header.to_string(), value);
+                if let Some((header_name, inline_type)) = headers.get(i) {
+                    // 内联标注优先于配置里的 `column_types`
+                    let declared_type = inline_type.as_deref()
+                        .or_else(|| config.column_types.get(header_name).map(|s| s.as_str()));
+                    row_obj.insert(header_name.clone(), parse_field_value(field, declared_type));
                 }
             }
-            
-            rows.push(Value::Object(row_obj));
+            Value::Object(row_obj)
+        };
+
+        // 第一遍：只扫描处理后每行的列名
+        let mut all_fieldnames = HashSet::new();
+        let mut any_row = false;
+        let mut reader = csv::Reader::from_reader(&mmap[..]);
+        for result in reader.records() {
+            let record = result.map_err(|e| format!("Failed to read CSV record: {}", e))?;
+            let row = row_from_record(&record);
+            if let Some(processed) = process_normal_row(&row, &config) {
+                any_row = true;
+                all_fieldnames.extend(processed.into_keys());
+            }
         }
-        
-        // 使用prepare_for_export处理数据（会提取payload_json中的字段）
-        let processed_data = prepare_for_export(&rows, &config);
-        
-        if processed_data.is_empty() {
+
+        if !any_row {
             return Err("No data to export after processing".to_string());
         }
-        
-        // 收集所有行的所有字段名
-        let mut all_fieldnames = std::collections::HashSet::new();
-        for row in &processed_data {
-            all_fieldnames.extend(row.keys().cloned());
-        }
-        
-        // 构建列名顺序：主表字段（按配置顺序）+ 扩展表字段（按字母顺序）
-        let main_fields = &config.main_table_fields;
-        let mut fieldnames: Vec<String> = Vec::new();
-        
-        // 1. 添加主表字段（按配置顺序，只包含实际存在的字段）
-        for field in main_fields {
-            if all_fieldnames.contains(field) {
-                fieldnames.push(field.clone());
+
+        let fieldnames = compute_fieldnames(all_fieldnames, false, &config);
+
+        // 第二遍：重新从同一份mmap里边读边写，不再收集成Vec
+        let mut sink = RowSink::open(OutputFormat::Csv, output_path, &fieldnames, &config.csv_dialect)?;
+        let mut reader = csv::Reader::from_reader(&mmap[..]);
+        for result in reader.records() {
+            let record = result.map_err(|e| format!("Failed to read CSV record: {}", e))?;
+            let row = row_from_record(&record);
+            if let Some(processed) = process_normal_row(&row, &config) {
+                sink.push_row(&fieldnames, &processed)?;
             }
         }
-        
-        // 2. 添加扩展表字段（按字母顺序）
-        let mut ext_fields: Vec<String> = all_fieldnames.iter()
-            .filter(|f| !main_fields.contains(f))
-            .cloned()
-            .collect();
-        ext_fields.sort();
-        fieldnames.extend(ext_fields);
-        
-        // 创建输出文件并写入UTF-8 BOM
-        let mut file = std::fs::File::create(output_path)
-            .map_err(|e| format!("Failed to create output file: {}", e))?;
-        
-        file.write_all(&[0xEF, 0xBB, 0xBF])
-            .map_err(|e| format!("Failed to write BOM: {}", e))?;
-        
-        // 创建CSV写入器
-        let mut wtr = Writer::from_writer(file);
-        
-        // 写入表头
-        wtr.write_record(&fieldnames)
-            .map_err(|e| format!("Failed to write header: {}", e))?;
-        
-        // 写入数据
-        for row in &processed_data {
-            let record: Vec<String> = fieldnames
-                .iter()
-                .map(|col| {
-                    let value = row.get(col);
-                    format_value(value)
-                })
-                .collect();
-            
-            wtr.write_record(&record)
-                .map_err(|e| format!("Failed to write record: {}", e))?;
-        }
-        
-        wtr.flush()
-            .map_err(|e| format!("Failed to flush CSV file: {}", e))?;
-        
-        Ok(())
+        sink.finish()
     } else {
-        // 如果没有payload_json或者是wide_table查询，直接复制文件
-        let csv_content = std::fs::read(csv_file_path)
-            .map_err(|e| format!("Failed to read CSV file: {}", e))?;
-        
-        // 创建输出文件并写入UTF-8 BOM + CSV内容
+        // 如果没有payload_json或者是wide_table查询，源CSV本身已经是最终形态，
+        // 直接基于mmap复制字节，不需要再过一遍解析/处理流水线。源文件的分隔符/
+        // 引号风格已经在它自己写出的时候定型了，这里没法事后改——只有write_bom
+        // 这个开关是单纯加/不加前缀字节，不需要重新解析内容就能生效
+        let config = load_config_with_override(config_path)?;
+
+        // 源CSV本身就可能带着这个工具自己写出时加的UTF-8 BOM（default_write_bom
+        // 默认就是true），先剥掉再按配置决定要不要重新加，否则BOM会被原样复制一份、
+        // 跟新写的BOM叠在一起，表头第一个字段会多出几个不可见字符
+        const BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+        let content = mmap.strip_prefix(BOM).unwrap_or(&mmap[..]);
+
         let mut output_file = std::fs::File::create(output_path)
             .map_err(|e| format!("Failed to create output file: {}", e))?;
-        
-        // 写入UTF-8 BOM（Excel兼容）
-        output_file.write_all(&[0xEF, 0xBB, 0xBF])
-            .map_err(|e| format!("Failed to write BOM: {}", e))?;
-        
-        // 直接写入CSV内容
-        output_file.write_all(&csv_content)
+
+        if config.csv_dialect.write_bom {
+            output_file.write_all(BOM)
+                .map_err(|e| format!("Failed to write BOM: {}", e))?;
+        }
+
+        output_file.write_all(content)
             .map_err(|e| format!("Failed to write CSV content: {}", e))?;
-        
+
         Ok(())
     }
 }
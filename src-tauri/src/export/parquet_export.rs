@@ -0,0 +1,210 @@
+use parquet::basic::{Compression, Repetition, Type as PhysicalType};
+use parquet::column::writer::ColumnWriter;
+use parquet::data_type::ByteArray;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::Type;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::format_value;
+
+/// 按列推断出来的parquet物理类型：全部非空值都能解析成整数就是 `Int64`，
+/// 数值但不全是整数就是 `Float64`，全是布尔值就是 `Boolean`，其余（包括已经
+/// 被 `format_timestamps_row` 格式化成字符串的时间戳列）一律退化成 `Utf8`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    Int64,
+    Float64,
+    Boolean,
+    Utf8,
+}
+
+impl ColumnType {
+    fn physical(self) -> PhysicalType {
+        match self {
+            ColumnType::Int64 => PhysicalType::INT64,
+            ColumnType::Float64 => PhysicalType::DOUBLE,
+            ColumnType::Boolean => PhysicalType::BOOLEAN,
+            ColumnType::Utf8 => PhysicalType::BYTE_ARRAY,
+        }
+    }
+}
+
+fn infer_column_type(processed_data: &[HashMap<String, Value>], field: &str) -> ColumnType {
+    let mut saw_non_null = false;
+    let mut all_bool = true;
+    let mut all_int = true;
+    let mut all_numeric = true;
+
+    for row in processed_data {
+        match row.get(field) {
+            None | Some(Value::Null) => continue,
+            Some(Value::Bool(_)) => {
+                saw_non_null = true;
+                all_int = false;
+                all_numeric = false;
+            }
+            Some(Value::Number(n)) => {
+                saw_non_null = true;
+                all_bool = false;
+                if n.as_i64().is_none() {
+                    all_int = false;
+                }
+            }
+            Some(_) => {
+                saw_non_null = true;
+                all_bool = false;
+                all_int = false;
+                all_numeric = false;
+            }
+        }
+    }
+
+    if !saw_non_null {
+        ColumnType::Utf8
+    } else if all_bool {
+        ColumnType::Boolean
+    } else if all_int {
+        ColumnType::Int64
+    } else if all_numeric {
+        ColumnType::Float64
+    } else {
+        ColumnType::Utf8
+    }
+}
+
+fn build_schema(fieldnames: &[String], types: &[ColumnType]) -> Result<Arc<Type>, String> {
+    let fields: Result<Vec<Arc<Type>>, String> = fieldnames
+        .iter()
+        .zip(types.iter())
+        .map(|(name, col_type)| {
+            Type::primitive_type_builder(name, col_type.physical())
+                .with_repetition(Repetition::OPTIONAL)
+                .build()
+                .map(Arc::new)
+                .map_err(|e| format!("构造parquet列类型失败: {}", e))
+        })
+        .collect();
+
+    let group = Type::group_type_builder("schema")
+        .with_fields(fields?)
+        .build()
+        .map_err(|e| format!("构造parquet schema失败: {}", e))?;
+
+    Ok(Arc::new(group))
+}
+
+fn write_column(writer: &mut ColumnWriter, col_type: ColumnType, rows: &[HashMap<String, Value>], field: &str) -> Result<(), String> {
+    match (writer, col_type) {
+        (ColumnWriter::Int64ColumnWriter(w), _) => {
+            let mut def_levels = Vec::with_capacity(rows.len());
+            let mut data = Vec::new();
+            for row in rows {
+                match row.get(field).and_then(|v| v.as_i64()) {
+                    Some(i) => {
+                        data.push(i);
+                        def_levels.push(1);
+                    }
+                    None => def_levels.push(0),
+                }
+            }
+            w.write_batch(&data, Some(&def_levels), None)
+                .map_err(|e| format!("写入INT64列失败: {}", e))?;
+        }
+        (ColumnWriter::DoubleColumnWriter(w), _) => {
+            let mut def_levels = Vec::with_capacity(rows.len());
+            let mut data = Vec::new();
+            for row in rows {
+                match row.get(field).and_then(|v| v.as_f64()) {
+                    Some(f) => {
+                        data.push(f);
+                        def_levels.push(1);
+                    }
+                    None => def_levels.push(0),
+                }
+            }
+            w.write_batch(&data, Some(&def_levels), None)
+                .map_err(|e| format!("写入DOUBLE列失败: {}", e))?;
+        }
+        (ColumnWriter::BoolColumnWriter(w), _) => {
+            let mut def_levels = Vec::with_capacity(rows.len());
+            let mut data = Vec::new();
+            for row in rows {
+                match row.get(field).and_then(|v| v.as_bool()) {
+                    Some(b) => {
+                        data.push(b);
+                        def_levels.push(1);
+                    }
+                    None => def_levels.push(0),
+                }
+            }
+            w.write_batch(&data, Some(&def_levels), None)
+                .map_err(|e| format!("写入BOOLEAN列失败: {}", e))?;
+        }
+        (ColumnWriter::ByteArrayColumnWriter(w), _) => {
+            let mut def_levels = Vec::with_capacity(rows.len());
+            let mut data = Vec::new();
+            for row in rows {
+                match row.get(field) {
+                    None | Some(Value::Null) => def_levels.push(0),
+                    Some(value) => {
+                        data.push(ByteArray::from(format_value(Some(value)).as_str()));
+                        def_levels.push(1);
+                    }
+                }
+            }
+            w.write_batch(&data, Some(&def_levels), None)
+                .map_err(|e| format!("写入BYTE_ARRAY列失败: {}", e))?;
+        }
+        _ => return Err("不支持的parquet列类型".to_string()),
+    }
+    Ok(())
+}
+
+/// 把已经跑完 `process_normal_row`/`process_wide_table_row` 的行数据写成
+/// 单个row group的Parquet文件：列顺序沿用调用方算好的 `fieldnames`（主表字段
+/// 优先、扩展字段按字母序），列的物理类型按 [`infer_column_type`] 逐列推断。
+/// Parquet是列式格式，类型推断需要看到整列的值，没办法像CSV/Json/Ndjson那样
+/// 边处理边写，所以这条路径的数据仍然整个攒在内存里（`export()` 单独为它
+/// 构造的 `processed_data`），也不需要像 [`crate::query::parquet_export`]
+/// 那样分批落row group
+pub fn write_parquet(
+    processed_data: &[HashMap<String, Value>],
+    fieldnames: &[String],
+    file_path: &str,
+) -> Result<(), String> {
+    let column_types: Vec<ColumnType> = fieldnames
+        .iter()
+        .map(|field| infer_column_type(processed_data, field))
+        .collect();
+
+    let schema = build_schema(fieldnames, &column_types)?;
+    let props = Arc::new(
+        WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build(),
+    );
+    let file = std::fs::File::create(file_path).map_err(|e| format!("创建输出文件失败: {}", e))?;
+    let mut writer = SerializedFileWriter::new(file, schema, props)
+        .map_err(|e| format!("创建parquet写入器失败: {}", e))?;
+
+    let mut row_group_writer = writer
+        .next_row_group()
+        .map_err(|e| format!("创建row group失败: {}", e))?;
+
+    for (field, col_type) in fieldnames.iter().zip(column_types.iter()) {
+        let mut column_writer = row_group_writer
+            .next_column()
+            .map_err(|e| format!("获取列写入器失败: {}", e))?
+            .ok_or_else(|| format!("列 {} 没有对应的写入器", field))?;
+        write_column(&mut column_writer, *col_type, processed_data, field)?;
+        column_writer.close().map_err(|e| format!("关闭列写入器失败: {}", e))?;
+    }
+
+    row_group_writer.close().map_err(|e| format!("关闭row group失败: {}", e))?;
+    writer.close().map_err(|e| format!("关闭parquet文件失败: {}", e))?;
+
+    Ok(())
+}
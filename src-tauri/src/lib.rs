@@ -0,0 +1,131 @@
+use tauri::Manager;
+use tauri_plugin_dialog::DialogExt;
+
+mod commands;
+// query/export/deploy 都依赖 ssh 连接，Cargo.toml 的 [features] 表需要把它们
+// 声明成 ssh 的隐式依赖（例如 `deploy = ["ssh"]`），否则单独开 deploy 关掉 ssh
+// 编不过
+#[cfg(feature = "ssh")]
+mod ssh;
+#[cfg(feature = "query")]
+mod query;
+#[cfg(feature = "export")]
+mod export;
+#[cfg(feature = "deploy")]
+mod deploy;
+mod vault;
+mod notify;
+// 菜单栏/系统托盘依赖桌面窗口概念（tray-icon 在移动端没有等价实现）
+#[cfg(desktop)]
+mod menu;
+
+/// 真正的应用入口：`main.rs` 和移动端入口都调用这里。桌面端由 `main()` 调用，
+/// 移动端由 `#[tauri::mobile_entry_point]` 调用——两边共享同一套 Builder 配置，
+/// 不需要各自维护一份
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    let mut builder = tauri::Builder::default()
+        .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_dialog::init());
+
+    // 移动端没有自动更新这一说（应用商店负责分发更新），updater 插件也没有
+    // 移动端实现，只在桌面构建里接入
+    #[cfg(desktop)]
+    {
+        builder = builder.plugin(tauri_plugin_updater::Builder::new().build());
+    }
+
+    builder
+        .invoke_handler(tauri::generate_handler![
+            #[cfg(feature = "ssh")]
+            commands::ssh_connect,
+            #[cfg(feature = "ssh")]
+            commands::ssh_disconnect,
+            #[cfg(feature = "ssh")]
+            commands::ssh_list_known_hosts,
+            #[cfg(feature = "ssh")]
+            commands::ssh_forget_known_host,
+            commands::vault_unlock,
+            commands::vault_save_credential,
+            commands::vault_list,
+            #[cfg(feature = "ssh")]
+            commands::vault_serve_as_agent,
+            #[cfg(feature = "ssh")]
+            commands::ssh_open_shell,
+            #[cfg(feature = "ssh")]
+            commands::shell_write,
+            #[cfg(feature = "ssh")]
+            commands::shell_resize,
+            #[cfg(feature = "ssh")]
+            commands::shell_close,
+            #[cfg(feature = "query")]
+            commands::execute_query,
+            #[cfg(feature = "query")]
+            commands::invalidate_query_cache,
+            #[cfg(feature = "export")]
+            commands::export_to_csv,
+            #[cfg(feature = "export")]
+            commands::reload_export_config,
+            #[cfg(feature = "export")]
+            commands::export_wide_table_direct,
+            #[cfg(feature = "export")]
+            commands::export_demand_results_direct,
+            #[cfg(feature = "export")]
+            commands::export_batch,
+            #[cfg(feature = "deploy")]
+            commands::check_deploy_status,
+            #[cfg(feature = "deploy")]
+            commands::deploy_application,
+            #[cfg(feature = "deploy")]
+            commands::uninstall_application,
+            #[cfg(feature = "deploy")]
+            commands::deploy_to_topology,
+            #[cfg(desktop)]
+            commands::check_for_updates,
+            #[cfg(desktop)]
+            commands::install_update,
+        ])
+        .setup(|_app| {
+            // 原生菜单栏和系统托盘都是桌面窗口概念，移动端没有等价物
+            #[cfg(desktop)]
+            {
+                let handle = _app.handle().clone();
+                let app_menu = menu::build_menu(&handle)?;
+                _app.set_menu(app_menu)?;
+                _app.on_menu_event(move |app, event| menu::handle_menu_event(app, event.id().as_ref()));
+                menu::build_tray(&handle)?;
+
+                // 未开启 ssh feature 时无所谓"残留连接"这回事，直接允许关闭
+                #[cfg(feature = "ssh")]
+                if let Some(window) = _app.get_webview_window("main") {
+                    window.on_window_event(move |event| {
+                        if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                            if ssh::SshClient::has_active_sessions() {
+                                api.prevent_close();
+                                let app = handle.clone();
+                                handle
+                                    .dialog()
+                                    .message("当前仍有未断开的远程连接，确定要退出吗？")
+                                    .title("确认退出")
+                                    .buttons(tauri_plugin_dialog::MessageDialogButtons::OkCancel)
+                                    .show(move |confirmed| {
+                                        if confirmed {
+                                            tauri::async_runtime::spawn(async move {
+                                                ssh::SshClient::disconnect_all().await;
+                                                app.exit(0);
+                                            });
+                                        }
+                                    });
+                            }
+                        }
+                    });
+                }
+            }
+
+            // SSH 连接在断开时会自动清理资源
+            // 查询用的远程数据库快照下载完就用 rm -f 删除，本地快照是 NamedTempFile，drop 时自动删除
+            Ok(())
+        })
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
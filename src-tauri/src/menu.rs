@@ -0,0 +1,86 @@
+use tauri::menu::{Menu, MenuBuilder, MenuItemBuilder, SubmenuBuilder};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+/// 原生菜单栏：File 下的几个动作和 Help 下的检查更新都只是往前端发一个事件，
+/// 真正的连接/断开/导出/更新逻辑仍然由前端已有的处理器驱动，这里不重复实现
+pub fn build_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<Menu<R>> {
+    let connect = MenuItemBuilder::with_id("menu-connect", "Connect").build(app)?;
+    let disconnect = MenuItemBuilder::with_id("menu-disconnect", "Disconnect").build(app)?;
+    let export = MenuItemBuilder::with_id("menu-export", "Export...").build(app)?;
+    let quit = MenuItemBuilder::with_id("menu-quit", "Quit").build(app)?;
+    let file_menu = SubmenuBuilder::new(app, "File")
+        .item(&connect)
+        .item(&disconnect)
+        .item(&export)
+        .separator()
+        .item(&quit)
+        .build()?;
+
+    let check_updates = MenuItemBuilder::with_id("menu-check-updates", "Check for Updates...").build(app)?;
+    let help_menu = SubmenuBuilder::new(app, "Help").item(&check_updates).build()?;
+
+    MenuBuilder::new(app).item(&file_menu).item(&help_menu).build()
+}
+
+/// 菜单项点击只是把事件转发给前端，具体动作（弹连接对话框、触发导出等）
+/// 由前端已有的事件监听器处理，和 shell/deploy 模块向前端发事件的方式一致
+pub fn handle_menu_event<R: Runtime>(app: &AppHandle<R>, event_id: &str) {
+    match event_id {
+        "menu-connect" => {
+            let _ = app.emit("menu-connect", ());
+        }
+        "menu-disconnect" => {
+            let _ = app.emit("menu-disconnect", ());
+        }
+        "menu-export" => {
+            let _ = app.emit("menu-export", ());
+        }
+        "menu-check-updates" => {
+            let _ = app.emit("menu-check-updates", ());
+        }
+        "menu-quit" => {
+            app.exit(0);
+        }
+        "tray-show" => {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+        }
+        "tray-hide" => {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.hide();
+            }
+        }
+        "tray-quit" => {
+            app.exit(0);
+        }
+        _ => {}
+    }
+}
+
+/// 系统托盘图标：双击显示主窗口，右键菜单里放 Show/Hide/Quit
+pub fn build_tray<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<()> {
+    let show = MenuItemBuilder::with_id("tray-show", "Show").build(app)?;
+    let hide = MenuItemBuilder::with_id("tray-hide", "Hide").build(app)?;
+    let quit = MenuItemBuilder::with_id("tray-quit", "Quit").build(app)?;
+    let tray_menu = MenuBuilder::new(app).item(&show).item(&hide).item(&quit).build()?;
+
+    TrayIconBuilder::new()
+        .menu(&tray_menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(|app, event| handle_menu_event(app, event.id().as_ref()))
+        .on_tray_icon_event(|tray, event| {
+            if let tauri::tray::TrayIconEvent::DoubleClick { .. } = event {
+                let app = tray.app_handle();
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
+            }
+        })
+        .build(app)?;
+
+    Ok(())
+}
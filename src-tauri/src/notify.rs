@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+
+/// 查询完成/失败时的通知渠道配置，由前端随查询参数一起传进来。`enabled` 为假
+/// 时 [`notify_query_result`] 直接跳过，不做任何网络请求；两个渠道都是可选的，
+/// 可以只配webhook、只配企业微信、或者两个都配
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotifyConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    #[serde(default)]
+    pub wechat_work: Option<WechatWorkConfig>,
+}
+
+/// 企业微信应用消息需要的配置：用 `corp_id`/`corp_secret` 换 `access_token`，
+/// 再用 `agent_id` 和目标用户列表把消息发给指定的人
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WechatWorkConfig {
+    pub corp_id: String,
+    pub corp_secret: String,
+    pub agent_id: i64,
+    /// 接收人列表，企业微信API要求拼成一个用 `|` 分隔的字符串
+    pub to_users: Vec<String>,
+}
+
+/// 查询完成或失败时要通知的内容，`notify_query_result` 据此拼出发给各渠道的
+/// 文本/JSON
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryNotification {
+    pub db_path: String,
+    pub success: bool,
+    pub row_count: Option<usize>,
+    pub file_size: Option<u64>,
+    pub elapsed_ms: u128,
+    pub error: Option<String>,
+}
+
+impl QueryNotification {
+    fn summary_text(&self) -> String {
+        if self.success {
+            let mut parts = vec![format!("查询完成: {}", self.db_path)];
+            if let Some(rows) = self.row_count {
+                parts.push(format!("行数: {}", rows));
+            }
+            if let Some(size) = self.file_size {
+                parts.push(format!("文件大小: {} 字节", size));
+            }
+            parts.push(format!("耗时: {} ms", self.elapsed_ms));
+            parts.join(" | ")
+        } else {
+            format!(
+                "查询失败: {} | 耗时: {} ms | 错误: {}",
+                self.db_path,
+                self.elapsed_ms,
+                self.error.as_deref().unwrap_or("未知错误")
+            )
+        }
+    }
+}
+
+/// 按配置把查询结果推给所有启用的渠道。单个渠道发送失败只记一条错误日志，
+/// 不会让调用方的查询流程因为通知失败而跟着报错——通知是旁路功能，不该反过来
+/// 影响查询本身的成功/失败判定
+pub async fn notify_query_result(config: &NotifyConfig, notification: &QueryNotification) {
+    if !config.enabled {
+        return;
+    }
+
+    if let Some(webhook_url) = &config.webhook_url {
+        if let Err(e) = send_webhook(webhook_url, notification).await {
+            eprintln!("推送webhook通知失败: {}", e);
+        }
+    }
+
+    if let Some(wechat_config) = &config.wechat_work {
+        if let Err(e) = send_wechat_work(wechat_config, notification).await {
+            eprintln!("推送企业微信通知失败: {}", e);
+        }
+    }
+}
+
+async fn send_webhook(webhook_url: &str, notification: &QueryNotification) -> Result<(), String> {
+    let client = reqwest::Client::new();
+    let response = client
+        .post(webhook_url)
+        .json(notification)
+        .send()
+        .await
+        .map_err(|e| format!("发送webhook请求失败: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("webhook返回非成功状态码: {}", response.status()));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct WechatTokenResponse {
+    errcode: i64,
+    errmsg: String,
+    access_token: Option<String>,
+}
+
+async fn fetch_wechat_access_token(config: &WechatWorkConfig) -> Result<String, String> {
+    let url = format!(
+        "https://qyapi.weixin.qq.com/cgi-bin/gettoken?corpid={}&corpsecret={}",
+        config.corp_id, config.corp_secret
+    );
+    let response: WechatTokenResponse = reqwest::get(&url)
+        .await
+        .map_err(|e| format!("请求企业微信access_token失败: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("解析企业微信access_token响应失败: {}", e))?;
+
+    if response.errcode != 0 {
+        return Err(format!("获取企业微信access_token失败: {} {}", response.errcode, response.errmsg));
+    }
+
+    response
+        .access_token
+        .ok_or_else(|| "企业微信access_token响应缺少access_token字段".to_string())
+}
+
+#[derive(Deserialize)]
+struct WechatSendResponse {
+    errcode: i64,
+    errmsg: String,
+}
+
+async fn send_wechat_work(config: &WechatWorkConfig, notification: &QueryNotification) -> Result<(), String> {
+    let access_token = fetch_wechat_access_token(config).await?;
+    let url = format!("https://qyapi.weixin.qq.com/cgi-bin/message/send?access_token={}", access_token);
+
+    let body = serde_json::json!({
+        "touser": config.to_users.join("|"),
+        "msgtype": "text",
+        "agentid": config.agent_id,
+        "text": { "content": notification.summary_text() },
+    });
+
+    let client = reqwest::Client::new();
+    let response: WechatSendResponse = client
+        .post(&url)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("发送企业微信消息失败: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("解析企业微信发送响应失败: {}", e))?;
+
+    if response.errcode != 0 {
+        return Err(format!("企业微信消息发送失败: {} {}", response.errcode, response.errmsg));
+    }
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use super::row_sink::CellValue;
+use parquet::basic::{Compression, Repetition, Type as PhysicalType};
+use parquet::column::writer::ColumnWriter;
+use parquet::data_type::ByteArray;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::Type;
+use std::sync::Arc;
+
+/// 一批缓冲多少行后落一个row group，内存占用和CSV流式写入路径同量级有界
+const BATCH_SIZE: usize = 2000;
+
+fn sqlite_type_to_parquet(sqlite_type: &str) -> PhysicalType {
+    let upper = sqlite_type.to_uppercase();
+    if upper.contains("INT") {
+        PhysicalType::INT64
+    } else if upper.contains("REAL") || upper.contains("FLOA") || upper.contains("DOUB") {
+        PhysicalType::DOUBLE
+    } else {
+        PhysicalType::BYTE_ARRAY
+    }
+}
+
+fn build_schema(columns: &[String], sqlite_types: &[String]) -> Result<Arc<Type>, String> {
+    let fields: Result<Vec<Arc<Type>>, String> = columns
+        .iter()
+        .zip(sqlite_types.iter())
+        .map(|(name, sqlite_type)| {
+            Type::primitive_type_builder(name, sqlite_type_to_parquet(sqlite_type))
+                .with_repetition(Repetition::OPTIONAL)
+                .build()
+                .map(Arc::new)
+                .map_err(|e| format!("构造parquet列类型失败: {}", e))
+        })
+        .collect();
+
+    let group = Type::group_type_builder("schema")
+        .with_fields(fields?)
+        .build()
+        .map_err(|e| format!("构造parquet schema失败: {}", e))?;
+
+    Ok(Arc::new(group))
+}
+
+/// 按行把数据喂进来、内部按列缓冲、攒够 `BATCH_SIZE` 行就落一个row group的
+/// Parquet写入器。列的物理类型在构造时从 `PRAGMA table_info` 查出的SQLite声明
+/// 类型推断而来（INTEGER -> INT64, REAL -> DOUBLE, 其余 -> BYTE_ARRAY），时间戳
+/// 列按原始整数写（不做时区换算），交给下游工具自行按epoch解释
+pub struct ParquetRowWriter {
+    writer: SerializedFileWriter<std::fs::File>,
+    columns: Vec<String>,
+    types: Vec<PhysicalType>,
+    buffer: Vec<Vec<CellValue>>,
+}
+
+impl ParquetRowWriter {
+    pub fn new(output_path: &str, columns: &[String], sqlite_types: &[String]) -> Result<Self, String> {
+        let schema = build_schema(columns, sqlite_types)?;
+        let props = Arc::new(
+            WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .build(),
+        );
+        let file = std::fs::File::create(output_path).map_err(|e| format!("创建输出文件失败: {}", e))?;
+        let writer = SerializedFileWriter::new(file, schema, props)
+            .map_err(|e| format!("创建parquet写入器失败: {}", e))?;
+        let types = sqlite_types.iter().map(|t| sqlite_type_to_parquet(t)).collect();
+
+        Ok(Self {
+            writer,
+            columns: columns.to_vec(),
+            types,
+            buffer: vec![Vec::new(); columns.len()],
+        })
+    }
+
+    pub fn push_row(&mut self, cells: Vec<CellValue>) -> Result<(), String> {
+        for (col, value) in cells.into_iter().enumerate() {
+            self.buffer[col].push(value);
+        }
+        if self.buffer[0].len() >= BATCH_SIZE {
+            self.flush_row_group()?;
+        }
+        Ok(())
+    }
+
+    fn flush_row_group(&mut self) -> Result<(), String> {
+        if self.buffer.iter().all(|col| col.is_empty()) {
+            return Ok(());
+        }
+
+        let mut row_group_writer = self
+            .writer
+            .next_row_group()
+            .map_err(|e| format!("创建row group失败: {}", e))?;
+
+        for col in 0..self.columns.len() {
+            let mut column_writer = row_group_writer
+                .next_column()
+                .map_err(|e| format!("获取列写入器失败: {}", e))?
+                .ok_or_else(|| format!("列 {} 没有对应的写入器", self.columns[col]))?;
+            write_column(&mut column_writer, self.types[col], &self.buffer[col])?;
+            column_writer
+                .close()
+                .map_err(|e| format!("关闭列写入器失败: {}", e))?;
+        }
+
+        row_group_writer
+            .close()
+            .map_err(|e| format!("关闭row group失败: {}", e))?;
+
+        for col_buf in self.buffer.iter_mut() {
+            col_buf.clear();
+        }
+        Ok(())
+    }
+
+    /// 落最后一个未满的row group并关闭文件
+    pub fn finish(mut self) -> Result<(), String> {
+        self.flush_row_group()?;
+        self.writer.close().map_err(|e| format!("关闭parquet文件失败: {}", e))?;
+        Ok(())
+    }
+}
+
+fn write_column(writer: &mut ColumnWriter, physical: PhysicalType, values: &[CellValue]) -> Result<(), String> {
+    match (writer, physical) {
+        (ColumnWriter::Int64ColumnWriter(w), _) => {
+            let mut def_levels = Vec::with_capacity(values.len());
+            let mut data = Vec::new();
+            for value in values {
+                match value.as_i64() {
+                    Some(i) => {
+                        data.push(i);
+                        def_levels.push(1);
+                    }
+                    None => def_levels.push(0),
+                }
+            }
+            w.write_batch(&data, Some(&def_levels), None)
+                .map_err(|e| format!("写入INT64列失败: {}", e))?;
+        }
+        (ColumnWriter::DoubleColumnWriter(w), _) => {
+            let mut def_levels = Vec::with_capacity(values.len());
+            let mut data = Vec::new();
+            for value in values {
+                match value.as_f64() {
+                    Some(f) => {
+                        data.push(f);
+                        def_levels.push(1);
+                    }
+                    None => def_levels.push(0),
+                }
+            }
+            w.write_batch(&data, Some(&def_levels), None)
+                .map_err(|e| format!("写入DOUBLE列失败: {}", e))?;
+        }
+        (ColumnWriter::ByteArrayColumnWriter(w), _) => {
+            let mut def_levels = Vec::with_capacity(values.len());
+            let mut data = Vec::new();
+            for value in values {
+                match value.as_text() {
+                    Some(s) => {
+                        data.push(ByteArray::from(s.as_str()));
+                        def_levels.push(1);
+                    }
+                    None => def_levels.push(0),
+                }
+            }
+            w.write_batch(&data, Some(&def_levels), None)
+                .map_err(|e| format!("写入BYTE_ARRAY列失败: {}", e))?;
+        }
+        _ => return Err("不支持的parquet列类型".to_string()),
+    }
+    Ok(())
+}
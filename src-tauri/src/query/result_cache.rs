@@ -0,0 +1,95 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+/// 查询结果缓存文件统一放这个目录下，文件名是key的哈希，内容是
+/// `[4字节CRC32][CBOR序列化的payload]`。payload里带 `serde_json::Value`
+/// （查询结果本身就是JSON），它的 `Deserialize` 实现走 `deserialize_any`，
+/// bincode这种非自描述格式会直接报 `DeserializeAnyNotSupported`，所以这里用
+/// CBOR——跟 [`crate::query::row_sink`] 导出CBOR用的是同一个 `serde_cbor`
+fn cache_dir() -> PathBuf {
+    std::env::temp_dir().join("remote_tool_query_cache")
+}
+
+fn cache_file_path(key: &str) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    cache_dir().join(format!("{:016x}.cache", hasher.finish()))
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// 读缓存：文件不存在、读不全、或者CRC校验不通过（截断/损坏）都视为未命中，
+/// 校验不通过时顺带把坏文件删掉，不会一直占着磁盘等下次继续读到同样的垃圾
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let path = cache_file_path(key);
+    let bytes = std::fs::read(&path).ok()?;
+    if bytes.len() < 4 {
+        return None;
+    }
+
+    let (crc_bytes, payload) = bytes.split_at(4);
+    let stored_crc = u32::from_le_bytes(crc_bytes.try_into().ok()?);
+    if crc32(payload) != stored_crc {
+        let _ = std::fs::remove_file(&path);
+        return None;
+    }
+
+    serde_cbor::from_slice(payload).ok()
+}
+
+/// 写缓存：CBOR序列化后算一遍CRC32存在payload前面，读的时候据此判断数据
+/// 有没有被截断或损坏
+pub fn store<T: Serialize>(key: &str, value: &T) -> Result<(), String> {
+    let payload = serde_cbor::to_vec(value).map_err(|e| format!("序列化查询结果缓存失败: {}", e))?;
+    let crc = crc32(&payload);
+
+    std::fs::create_dir_all(cache_dir()).map_err(|e| format!("创建查询结果缓存目录失败: {}", e))?;
+
+    let mut bytes = Vec::with_capacity(4 + payload.len());
+    bytes.extend_from_slice(&crc.to_le_bytes());
+    bytes.extend_from_slice(&payload);
+
+    std::fs::write(cache_file_path(key), bytes).map_err(|e| format!("写入查询结果缓存失败: {}", e))
+}
+
+/// 显式失效：供调用方强制下一次查询跳过缓存重新执行（比如用户点了"刷新"）
+pub fn invalidate(key: &str) {
+    let _ = std::fs::remove_file(cache_file_path(key));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 跟 `execute_sql_query` 实际缓存的payload类型保持一致：之前用bincode序列化
+    // 这个元组会在load时静默失败（serde_json::Value走deserialize_any，bincode
+    // 不支持），store永远白写、load永远未命中。这里验证store之后第二次load确实
+    // 能原样读回来，而不是只测"不panic"
+    type QueryPayload = (Vec<serde_json::Value>, Vec<String>, Vec<String>);
+
+    #[test]
+    fn store_then_load_round_trips_and_hits_cache() {
+        let key = "result_cache_test::round_trip";
+        invalidate(key);
+
+        let payload: QueryPayload = (
+            vec![serde_json::json!({"id": 1, "device_sn": "SN001", "activePower": 12.5})],
+            vec!["id".to_string(), "device_sn".to_string(), "activePower".to_string()],
+            vec!["INTEGER".to_string(), "TEXT".to_string(), "REAL".to_string()],
+        );
+
+        store(key, &payload).expect("写入缓存失败");
+
+        let cached: QueryPayload = load(key).expect("缓存应当命中，但load返回了None");
+        assert_eq!(cached, payload);
+
+        invalidate(key);
+        assert!(load::<QueryPayload>(key).is_none());
+    }
+}
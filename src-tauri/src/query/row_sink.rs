@@ -0,0 +1,230 @@
+use super::xlsx_export;
+use super::parquet_export;
+use chrono::NaiveDateTime;
+use std::io::Write as _;
+
+/// 一行查询结果里某一列的值，保留原始类型信息。CSV/XLSX/JSONL/CBOR/Parquet 五种
+/// 输出格式都从同一份由 [`super::row_to_cells`] 转换出来的 `CellValue` 取数据，
+/// 不需要每种格式各自重新读一遍 `rusqlite::Row`。
+pub enum CellValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    /// 时间戳列（宽表 `local_timestamp` 是毫秒，需量结果 `timestamp` 是秒）。
+    /// `raw` 保留SQLite里的原始整数值（Parquet路径直接写这个，不做时区换算），
+    /// `naive` 是换算成东八区后的日期时间（CSV/XLSX/JSONL/CBOR 用这个）。
+    Timestamp { raw: i64, naive: NaiveDateTime },
+}
+
+impl CellValue {
+    pub(super) fn as_i64(&self) -> Option<i64> {
+        match self {
+            CellValue::Integer(i) => Some(*i),
+            CellValue::Timestamp { raw, .. } => Some(*raw),
+            _ => None,
+        }
+    }
+
+    pub(super) fn as_f64(&self) -> Option<f64> {
+        match self {
+            CellValue::Real(f) => Some(*f),
+            CellValue::Integer(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
+    pub(super) fn as_text(&self) -> Option<String> {
+        match self {
+            CellValue::Text(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+fn timestamp_format(ms_timestamp: bool) -> &'static str {
+    if ms_timestamp { "%Y-%m-%d %H:%M:%S%.3f" } else { "%Y-%m-%d %H:%M:%S" }
+}
+
+fn open_output_file(output_path: &str, append: bool) -> Result<std::fs::File, String> {
+    if append {
+        std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(output_path)
+            .map_err(|e| format!("打开输出文件失败: {}", e))
+    } else {
+        std::fs::File::create(output_path).map_err(|e| format!("创建输出文件失败: {}", e))
+    }
+}
+
+/// CSV 路径：沿用原来的单引号前缀兼容写法，强制Excel把时间戳列识别为文本
+fn csv_record_from_cells(cells: &[CellValue], ms_timestamp: bool) -> Vec<String> {
+    cells.iter().map(|cell| match cell {
+        CellValue::Null => String::new(),
+        CellValue::Integer(i) => i.to_string(),
+        CellValue::Real(f) => f.to_string(),
+        CellValue::Text(s) => s.clone(),
+        CellValue::Timestamp { naive, .. } => format!("'{}", naive.format(timestamp_format(ms_timestamp))),
+    }).collect()
+}
+
+fn write_cell_to_xlsx(sink: &mut xlsx_export::XlsxRowWriter, col: u16, cell: &CellValue) -> Result<(), String> {
+    match cell {
+        CellValue::Null => sink.write_cell_string(col, ""),
+        CellValue::Integer(i) => sink.write_cell_number(col, *i as f64),
+        CellValue::Real(f) => sink.write_cell_number(col, *f),
+        CellValue::Text(s) => sink.write_cell_string(col, s),
+        CellValue::Timestamp { naive, .. } => sink.write_cell_datetime(col, *naive),
+    }
+}
+
+/// JSONL/CBOR 路径：保留真实的数字/字符串类型，时间戳列写成格式化后的东八区
+/// 字符串（不再是CSV那种单引号前缀，下游工具不需要这个Excel专用的权宜手段）
+fn cell_to_json(cell: &CellValue, ms_timestamp: bool) -> serde_json::Value {
+    match cell {
+        CellValue::Null => serde_json::Value::Null,
+        CellValue::Integer(i) => serde_json::Value::Number((*i).into()),
+        CellValue::Real(f) => serde_json::Number::from_f64(*f)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        CellValue::Text(s) => serde_json::Value::String(s.clone()),
+        CellValue::Timestamp { naive, .. } => {
+            serde_json::Value::String(naive.format(timestamp_format(ms_timestamp)).to_string())
+        }
+    }
+}
+
+fn cell_to_cbor(cell: &CellValue, ms_timestamp: bool) -> serde_cbor::Value {
+    match cell {
+        CellValue::Null => serde_cbor::Value::Null,
+        CellValue::Integer(i) => serde_cbor::Value::Integer(*i as i128),
+        CellValue::Real(f) => serde_cbor::Value::Float(*f),
+        CellValue::Text(s) => serde_cbor::Value::Text(s.clone()),
+        CellValue::Timestamp { naive, .. } => {
+            serde_cbor::Value::Text(naive.format(timestamp_format(ms_timestamp)).to_string())
+        }
+    }
+}
+
+fn write_jsonl_row(file: &mut std::fs::File, columns: &[String], cells: &[CellValue], ms_timestamp: bool) -> Result<(), String> {
+    let mut map = serde_json::Map::with_capacity(columns.len());
+    for (col, cell) in columns.iter().zip(cells.iter()) {
+        map.insert(col.clone(), cell_to_json(cell, ms_timestamp));
+    }
+    serde_json::to_writer(&mut *file, &serde_json::Value::Object(map))
+        .map_err(|e| format!("写入JSONL行失败: {}", e))?;
+    file.write_all(b"\n").map_err(|e| format!("写入JSONL换行失败: {}", e))?;
+    Ok(())
+}
+
+// CBOR 按行连续写（CBOR sequence），每个 map 值自带长度前缀，不需要分隔符
+fn write_cbor_row(file: &mut std::fs::File, columns: &[String], cells: &[CellValue], ms_timestamp: bool) -> Result<(), String> {
+    let mut map = std::collections::BTreeMap::new();
+    for (col, cell) in columns.iter().zip(cells.iter()) {
+        map.insert(serde_cbor::Value::Text(col.clone()), cell_to_cbor(cell, ms_timestamp));
+    }
+    serde_cbor::to_writer(&mut *file, &serde_cbor::Value::Map(map))
+        .map_err(|e| format!("写入CBOR记录失败: {}", e))
+}
+
+/// 五种导出格式共用的行级写入器：每种格式内部维护自己的底层writer，对外暴露
+/// 统一的 `push_row`/`finish` 接口，`export_wide_table_direct`/
+/// `export_demand_results_direct` 不需要关心格式差异
+pub enum ExportSink {
+    Csv(csv::Writer<std::fs::File>),
+    Xlsx(xlsx_export::XlsxRowWriter),
+    Jsonl(std::fs::File),
+    Cbor(std::fs::File),
+    Parquet(parquet_export::ParquetRowWriter),
+}
+
+impl ExportSink {
+    pub fn open(
+        output_format: &str,
+        output_path: &str,
+        sheet_name: &str,
+        columns: &[String],
+        sqlite_types: &[String],
+    ) -> Result<Self, String> {
+        Self::open_impl(output_format, output_path, sheet_name, columns, sqlite_types, false)
+    }
+
+    /// 续传/分片导出专用：`append` 为真时打开已有文件继续往后写（CSV不重复
+    /// 写表头和BOM，JSONL/CBOR直接追加），不重新创建文件。XLSX/Parquet不支持
+    /// 增量追加写入，`append` 为真时对这两种格式直接报错。
+    pub fn open_or_append(
+        output_format: &str,
+        output_path: &str,
+        sheet_name: &str,
+        columns: &[String],
+        sqlite_types: &[String],
+        append: bool,
+    ) -> Result<Self, String> {
+        Self::open_impl(output_format, output_path, sheet_name, columns, sqlite_types, append)
+    }
+
+    fn open_impl(
+        output_format: &str,
+        output_path: &str,
+        sheet_name: &str,
+        columns: &[String],
+        sqlite_types: &[String],
+        append: bool,
+    ) -> Result<Self, String> {
+        match output_format {
+            "xlsx" => {
+                if append {
+                    return Err("XLSX 格式不支持增量追加写入，分片续传导出请使用 csv/jsonl/cbor".to_string());
+                }
+                Ok(Self::Xlsx(xlsx_export::XlsxRowWriter::new(sheet_name, columns)?))
+            }
+            "jsonl" => Ok(Self::Jsonl(open_output_file(output_path, append)?)),
+            "cbor" => Ok(Self::Cbor(open_output_file(output_path, append)?)),
+            "parquet" => {
+                if append {
+                    return Err("Parquet 格式不支持增量追加写入，分片续传导出请使用 csv/jsonl/cbor".to_string());
+                }
+                Ok(Self::Parquet(parquet_export::ParquetRowWriter::new(
+                    output_path, columns, sqlite_types,
+                )?))
+            }
+            _ => {
+                if append {
+                    Ok(Self::Csv(super::append_csv_writer(output_path)?))
+                } else {
+                    Ok(Self::Csv(super::new_csv_writer(output_path, columns)?))
+                }
+            }
+        }
+    }
+
+    pub fn push_row(&mut self, columns: &[String], cells: Vec<CellValue>, ms_timestamp: bool) -> Result<(), String> {
+        match self {
+            Self::Csv(writer) => {
+                let record = csv_record_from_cells(&cells, ms_timestamp);
+                writer.write_record(&record).map_err(|e| format!("写入CSV记录失败: {}", e))
+            }
+            Self::Xlsx(writer) => {
+                for (i, cell) in cells.iter().enumerate() {
+                    write_cell_to_xlsx(writer, i as u16, cell)?;
+                }
+                writer.next_row();
+                Ok(())
+            }
+            Self::Jsonl(file) => write_jsonl_row(file, columns, &cells, ms_timestamp),
+            Self::Cbor(file) => write_cbor_row(file, columns, &cells, ms_timestamp),
+            Self::Parquet(writer) => writer.push_row(cells),
+        }
+    }
+
+    pub fn finish(self, output_path: &str, row_count: usize, time_range: (&str, &str)) -> Result<(), String> {
+        match self {
+            Self::Csv(mut writer) => writer.flush().map_err(|e| format!("刷新CSV文件失败: {}", e)),
+            Self::Xlsx(writer) => writer.finish(output_path, row_count, time_range),
+            Self::Jsonl(mut file) => file.flush().map_err(|e| format!("刷新JSONL文件失败: {}", e)),
+            Self::Cbor(mut file) => file.flush().map_err(|e| format!("刷新CBOR文件失败: {}", e)),
+            Self::Parquet(writer) => writer.finish(),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use chrono::NaiveDateTime;
+use rust_xlsxwriter::{Format, Workbook, Worksheet};
+
+/// 按行流式写入的XLSX输出：`export_wide_table_direct`/`export_demand_results_direct`
+/// 共用，时间戳列写成真正的日期时间单元格，数字列写成真正的数字单元格——不再需要
+/// CSV那套单引号前缀 + QUOTE_NONNUMERIC的权宜手段。调用方对每一行依次调用
+/// `write_cell_*`，然后 `next_row`，最后 `finish` 追加一张metadata sheet（列名、
+/// 行数、查询的时间范围）并保存。`Workbook` 在 `finish` 保存之前把整张工作表的
+/// 单元格都留在内存里，内存占用随行数增长，不像CSV流式写入路径那样有界。
+pub struct XlsxRowWriter {
+    workbook: Workbook,
+    headers: Vec<String>,
+    row: u32,
+    datetime_format: Format,
+}
+
+impl XlsxRowWriter {
+    pub fn new(sheet_name: &str, headers: &[String]) -> Result<Self, String> {
+        let mut workbook = Workbook::new();
+        {
+            let worksheet = workbook
+                .add_worksheet()
+                .set_name(truncate_sheet_name(sheet_name))
+                .map_err(|e| format!("设置工作表名称失败: {}", e))?;
+            for (col, header) in headers.iter().enumerate() {
+                worksheet
+                    .write_string(0, col as u16, header)
+                    .map_err(|e| format!("写入表头失败: {}", e))?;
+            }
+        }
+
+        Ok(Self {
+            workbook,
+            headers: headers.to_vec(),
+            row: 1,
+            datetime_format: Format::new().set_num_format("yyyy-mm-dd hh:mm:ss.000"),
+        })
+    }
+
+    fn worksheet(&mut self) -> Result<&mut Worksheet, String> {
+        self.workbook
+            .worksheet_from_index(0)
+            .map_err(|e| format!("获取工作表失败: {}", e))
+    }
+
+    pub fn write_cell_string(&mut self, col: u16, value: &str) -> Result<(), String> {
+        let row = self.row;
+        self.worksheet()?
+            .write_string(row, col, value)
+            .map_err(|e| format!("写入单元格失败: {}", e))?;
+        Ok(())
+    }
+
+    pub fn write_cell_number(&mut self, col: u16, value: f64) -> Result<(), String> {
+        let row = self.row;
+        self.worksheet()?
+            .write_number(row, col, value)
+            .map_err(|e| format!("写入数字单元格失败: {}", e))?;
+        Ok(())
+    }
+
+    pub fn write_cell_datetime(&mut self, col: u16, value: NaiveDateTime) -> Result<(), String> {
+        let row = self.row;
+        let format = self.datetime_format.clone();
+        self.worksheet()?
+            .write_datetime_with_format(row, col, value, &format)
+            .map_err(|e| format!("写入时间单元格失败: {}", e))?;
+        Ok(())
+    }
+
+    pub fn next_row(&mut self) {
+        self.row += 1;
+    }
+
+    /// 追加metadata sheet并保存到 `output_path`，消费self
+    pub fn finish(mut self, output_path: &str, row_count: usize, time_range: (&str, &str)) -> Result<(), String> {
+        let metadata = self
+            .workbook
+            .add_worksheet()
+            .set_name("metadata")
+            .map_err(|e| format!("设置元数据工作表名称失败: {}", e))?;
+
+        metadata.write_string(0, 0, "column").map_err(|e| e.to_string())?;
+        metadata.write_string(0, 1, "index").map_err(|e| e.to_string())?;
+        for (i, header) in self.headers.iter().enumerate() {
+            metadata
+                .write_string((i + 1) as u32, 0, header)
+                .map_err(|e| format!("写入元数据失败: {}", e))?;
+            metadata
+                .write_number((i + 1) as u32, 1, i as f64)
+                .map_err(|e| format!("写入元数据失败: {}", e))?;
+        }
+
+        let summary_row = (self.headers.len() + 2) as u32;
+        metadata.write_string(summary_row, 0, "row_count").map_err(|e| e.to_string())?;
+        metadata.write_number(summary_row, 1, row_count as f64).map_err(|e| e.to_string())?;
+        metadata.write_string(summary_row + 1, 0, "time_range_start").map_err(|e| e.to_string())?;
+        metadata.write_string(summary_row + 1, 1, time_range.0).map_err(|e| e.to_string())?;
+        metadata.write_string(summary_row + 2, 0, "time_range_end").map_err(|e| e.to_string())?;
+        metadata.write_string(summary_row + 2, 1, time_range.1).map_err(|e| e.to_string())?;
+
+        self.workbook
+            .save(output_path)
+            .map_err(|e| format!("保存XLSX文件失败: {}", e))?;
+
+        Ok(())
+    }
+}
+
+fn truncate_sheet_name(name: &str) -> &str {
+    // Excel 工作表名最长 31 个字符
+    match name.char_indices().nth(31) {
+        Some((idx, _)) => &name[..idx],
+        None => name,
+    }
+}
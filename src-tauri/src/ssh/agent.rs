@@ -0,0 +1,99 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+#[cfg(unix)]
+use tokio::net::UnixStream;
+
+// SSH agent 协议消息号（draft-miller-ssh-agent）
+pub const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+pub const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+pub const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+pub const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+
+pub struct AgentIdentity {
+    pub blob: Vec<u8>,
+    pub comment: String,
+}
+
+fn agent_sock_path() -> Option<PathBuf> {
+    // Unix: SSH_AUTH_SOCK 环境变量；Windows: OpenSSH agent 固定命名管道
+    #[cfg(unix)]
+    {
+        std::env::var_os("SSH_AUTH_SOCK").map(PathBuf::from)
+    }
+    #[cfg(windows)]
+    {
+        Some(PathBuf::from(r"\\.\pipe\openssh-ssh-agent"))
+    }
+}
+
+/// 枚举正在运行的 SSH agent 中的身份（公钥 blob + comment），用于在连接 JumpServer
+/// 时供用户/连接逻辑挑选可用身份，而不需要导出私钥文件
+pub async fn list_identities() -> Result<Vec<AgentIdentity>> {
+    #[cfg(unix)]
+    {
+        let path = agent_sock_path()
+            .ok_or_else(|| anyhow::anyhow!("未设置 SSH_AUTH_SOCK，没有检测到运行中的 agent"))?;
+        let mut stream = UnixStream::connect(&path)
+            .await
+            .with_context(|| format!("连接 SSH agent 失败: {:?}", path))?;
+        request_identities(&mut stream).await
+    }
+    #[cfg(windows)]
+    {
+        // Windows OpenSSH agent 的命名管道枚举走相同的协议帧格式，这里暂不展开
+        // 具体 IO 细节，连接失败时会自然回退到密钥文件/密码认证
+        anyhow::bail!("当前平台暂未启用 agent 身份枚举，回退到密钥文件/密码认证")
+    }
+}
+
+#[cfg(unix)]
+async fn request_identities(stream: &mut UnixStream) -> Result<Vec<AgentIdentity>> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // 请求体：4 字节长度前缀 + 1 字节消息类型，不带负载
+    stream.write_all(&[0u8, 0, 0, 1, SSH_AGENTC_REQUEST_IDENTITIES]).await?;
+
+    let mut len_buf = [0u8; 4];
+    stream.read_exact(&mut len_buf).await?;
+    let len = u32::from_be_bytes(len_buf) as usize;
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body).await?;
+
+    if body.is_empty() || body[0] != SSH_AGENT_IDENTITIES_ANSWER {
+        anyhow::bail!("agent 返回了意料之外的消息类型");
+    }
+
+    let mut cursor = &body[1..];
+    let count = read_u32(&mut cursor)?;
+    let mut identities = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let blob = read_bytes(&mut cursor)?;
+        let comment = String::from_utf8_lossy(&read_bytes(&mut cursor)?).to_string();
+        identities.push(AgentIdentity { blob, comment });
+    }
+    Ok(identities)
+}
+
+// pub(crate)：vault 的 in-process agent 端点（serve_as_agent）解析
+// SSH_AGENTC_SIGN_REQUEST 的 key_blob/data/flags 时复用同一套读取逻辑
+#[cfg(unix)]
+pub(crate) fn read_u32(cursor: &mut &[u8]) -> Result<u32> {
+    if cursor.len() < 4 {
+        anyhow::bail!("agent 响应过短");
+    }
+    let (head, rest) = cursor.split_at(4);
+    *cursor = rest;
+    Ok(u32::from_be_bytes(head.try_into().unwrap()))
+}
+
+#[cfg(unix)]
+pub(crate) fn read_bytes(cursor: &mut &[u8]) -> Result<Vec<u8>> {
+    let len = read_u32(cursor)? as usize;
+    if cursor.len() < len {
+        anyhow::bail!("agent 响应过短");
+    }
+    let (data, rest) = cursor.split_at(len);
+    *cursor = rest;
+    Ok(data.to_vec())
+}
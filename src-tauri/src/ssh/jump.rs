@@ -0,0 +1,136 @@
+use super::known_hosts::HostKeyStatus;
+use super::shell::{authenticate, ShellAuthHandler};
+use super::SshConfig;
+use anyhow::{Context, Result};
+use russh::client::{self, Handle};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// async-ssh2-tokio 的 Client 只接受真实的 TCP 地址，没有暴露"在已有连接上层叠
+// 一条新 SSH 会话"的接口，所以无法直接把它连到跳板转发出的 channel 上。
+// 这里换一种等价实现：用 russh 建立到每一跳跳板的原始连接（与交互式 shell 复用
+// 同一套认证逻辑），在最后一跳跳板上打开一条 direct-tcpip 转发 channel 指向
+// 最终目标，再起一个本地回环端口把该 channel 原样转发出来，最终让
+// async_ssh2_tokio::Client 像连接本地端口一样连接到目标主机 —— 效果等价于
+// `ssh -J bastion1,bastion2 target`。
+
+/// 依次连接 `jump_hosts` 中的每一跳跳板机，并在最后一跳上打开一条指向
+/// `target` 的转发 channel，通过本地回环端口转发出来。
+/// 返回值为 "该连到哪个本地地址"、`target.verify_host_key` 为真时校验到的目标主机
+/// 指纹（供调用方继续 pin 到真正承载业务流量的连接上），以及一个需要在会话结束前
+/// 持续持有的后台任务句柄。
+pub async fn open_tunnel(
+    app: &tauri::AppHandle,
+    jump_hosts: &[SshConfig],
+    target: &SshConfig,
+) -> Result<(String, u16, Option<String>, tokio::task::JoinHandle<()>)> {
+    let mut bastion: Option<Handle<ShellAuthHandler>> = None;
+    for hop in jump_hosts {
+        let next = connect_hop(bastion.as_ref(), hop).await?;
+        bastion = Some(next);
+    }
+    let bastion = bastion.expect("open_tunnel 仅应在 jump_hosts 非空时调用");
+
+    // 跳板各跳自身不纳入校验范围（见 commands.rs 里 hop 的 verify_host_key: false），
+    // 但跳板后的目标主机之前完全没有校验——这里额外打开一条一次性探测 channel，
+    // 复用和直连场景相同的 TOFU 指纹比对/记录逻辑，探测完即关，不影响下面真正的转发 channel
+    let target_fingerprint = if target.verify_host_key {
+        let probe = bastion
+            .channel_open_direct_tcpip(&target.host, target.port as u32, "127.0.0.1", 0)
+            .await
+            .with_context(|| format!("打开到 {}:{} 的探测 channel 失败", target.host, target.port))?;
+        match super::known_hosts::verify_via_channel(app, &target.host, target.port, probe).await? {
+            HostKeyStatus::New { fingerprint } => Some(fingerprint),
+            HostKeyStatus::Match { fingerprint } => Some(fingerprint),
+            HostKeyStatus::Changed { old_fingerprint, new_fingerprint } => {
+                anyhow::bail!(
+                    "主机密钥已改变，拒绝连接（可能是密钥轮换，也可能是中间人攻击）\n\n\
+                    主机: {}:{}\n旧指纹: {}\n新指纹: {}\n\n\
+                    如果确认是服务器密钥轮换，请先调用 ssh_forget_known_host 清除旧记录后重试",
+                    target.host, target.port, old_fingerprint, new_fingerprint
+                );
+            }
+        }
+    } else {
+        None
+    };
+
+    let channel = bastion
+        .channel_open_direct_tcpip(&target.host, target.port as u32, "127.0.0.1", 0)
+        .await
+        .with_context(|| format!("通过跳板打开到 {}:{} 的转发 channel 失败", target.host, target.port))?;
+
+    // 本地回环监听：把转发 channel 的数据原样搬运到一个只接受单次连接的本地端口，
+    // 这样 async_ssh2_tokio::Client::connect 就能像连接真实主机一样连接过去
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await.context("绑定本地转发端口失败")?;
+    let local_port = listener.local_addr().context("读取本地转发端口失败")?.port();
+
+    let relay = tokio::spawn(async move {
+        // 只需要转发一次目标连接（async_ssh2_tokio::Client 建立完连接后就不会再重连）
+        let (stream, _) = match listener.accept().await {
+            Ok(pair) => pair,
+            Err(_) => return,
+        };
+        pump(stream, channel).await;
+        // bastion 在这里被 drop，关闭整条跳板链
+        drop(bastion);
+    });
+
+    Ok(("127.0.0.1".to_string(), local_port, target_fingerprint, relay))
+}
+
+/// 在一条本地 TCP 流和一条 russh channel 之间双向搬运字节，直到任意一端关闭
+async fn pump(stream: TcpStream, mut channel: russh::Channel<client::Msg>) {
+    let (mut read_half, mut write_half) = stream.into_split();
+    let mut read_buf = [0u8; 16 * 1024];
+    loop {
+        tokio::select! {
+            n = read_half.read(&mut read_buf) => {
+                match n {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        if channel.data(&read_buf[..n]).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+            msg = channel.wait() => {
+                match msg {
+                    Some(russh::ChannelMsg::Data { data }) => {
+                        if write_half.write_all(&data).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(russh::ChannelMsg::Eof) | None => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+    let _ = channel.close().await;
+}
+
+/// 连接并认证一跳跳板机：`via` 为空时直接 TCP 拨号，否则经上一跳转发
+async fn connect_hop(via: Option<&Handle<ShellAuthHandler>>, hop: &SshConfig) -> Result<Handle<ShellAuthHandler>> {
+    let russh_config = std::sync::Arc::new(client::Config::default());
+
+    let mut handle = match via {
+        None => client::connect(russh_config, (hop.host.as_str(), hop.port), ShellAuthHandler)
+            .await
+            .with_context(|| format!("连接跳板机失败: {}:{}", hop.host, hop.port))?,
+        Some(prev) => {
+            let channel = prev
+                .channel_open_direct_tcpip(&hop.host, hop.port as u32, "127.0.0.1", 0)
+                .await
+                .with_context(|| format!("经上一跳打开到跳板 {}:{} 的转发 channel 失败", hop.host, hop.port))?;
+            client::connect_stream(russh_config, channel.into_stream(), ShellAuthHandler)
+                .await
+                .with_context(|| format!("经上一跳连接跳板机失败: {}:{}", hop.host, hop.port))?
+        }
+    };
+
+    authenticate(&mut handle, hop).await?;
+    Ok(handle)
+}
+
@@ -0,0 +1,216 @@
+use anyhow::{Context, Result};
+use russh::client::{self, Handle};
+use serde::Serialize;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// 主机密钥指纹校验结果。`New`/`Match` 都带上实际指纹，供调用方把同一份指纹
+/// 继续传给承载业务流量的连接做 pinning（而不是校验完就丢弃，留一个 TOCTOU 窗口）
+pub enum HostKeyStatus {
+    /// 首次见到该主机，已按 TOFU（Trust On First Use）记录
+    New { fingerprint: String },
+    /// 指纹与已记录的一致
+    Match { fingerprint: String },
+    /// 指纹和已记录的不一致，可能是密钥轮换，也可能是中间人攻击
+    Changed {
+        old_fingerprint: String,
+        new_fingerprint: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinnedHostKey {
+    pub host: String,
+    pub port: u16,
+    pub fingerprint: String,
+}
+
+fn known_hosts_path(app: &tauri::AppHandle) -> Result<PathBuf> {
+    use tauri::Manager;
+    let dir = app
+        .path()
+        .app_data_dir()
+        .with_context(|| "无法解析应用数据目录")?;
+    std::fs::create_dir_all(&dir).with_context(|| format!("创建应用数据目录失败: {:?}", dir))?;
+    Ok(dir.join("known_hosts"))
+}
+
+/// 主机在 known_hosts 文件中的标识：端口为 22 时只写主机名，
+/// 否则按 OpenSSH 习惯写成 `[host]:port`
+fn host_pattern(host: &str, port: u16) -> String {
+    if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+/// known_hosts 文件每行格式：`host_pattern fingerprint`
+/// 注意：这里没有照搬 OpenSSH 完整的 `host keytype base64key` 格式，而是只保存
+/// SHA256 指纹 —— 对于"发现变化就拒绝"这个 TOFU 场景已经足够，且不需要再实现
+/// 一遍公钥的 SSH wire format 编码
+fn read_entries(app: &tauri::AppHandle) -> Result<Vec<(String, String)>> {
+    let path = known_hosts_path(app)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path).with_context(|| format!("读取 known_hosts 失败: {:?}", path))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let mut parts = line.splitn(2, ' ');
+            let pattern = parts.next()?.to_string();
+            let fingerprint = parts.next()?.to_string();
+            Some((pattern, fingerprint))
+        })
+        .collect())
+}
+
+fn write_entries(app: &tauri::AppHandle, entries: &[(String, String)]) -> Result<()> {
+    let path = known_hosts_path(app)?;
+    let mut file = std::fs::File::create(&path).with_context(|| format!("写入 known_hosts 失败: {:?}", path))?;
+    for (pattern, fingerprint) in entries {
+        writeln!(file, "{} {}", pattern, fingerprint)?;
+    }
+    Ok(())
+}
+
+/// 空的 russh Handler，仅用于借助 russh 的握手流程拿到服务器公钥指纹，
+/// 真正的账号认证仍然交给 async-ssh2-tokio 的 Client 完成（见 SshClient::connect）。
+/// 这里把服务器公钥通过 oneshot 传出去，而不是在 Handler 内部直接做信任判断，
+/// 这样指纹比对、记录逻辑可以复用普通函数，便于单独测试/调用。
+struct FingerprintHandler {
+    fingerprint_tx: Option<tokio::sync::oneshot::Sender<String>>,
+}
+
+#[async_trait::async_trait]
+impl client::Handler for FingerprintHandler {
+    type Error = russh::Error;
+
+    async fn check_server_key(&mut self, server_public_key: &russh_keys::key::PublicKey) -> Result<bool, Self::Error> {
+        if let Some(tx) = self.fingerprint_tx.take() {
+            let _ = tx.send(server_public_key.fingerprint());
+        }
+        // 这里只是为了拿指纹，真正的信任判断在 verify / compare_and_record 里做；先放行完成握手
+        Ok(true)
+    }
+}
+
+/// 建立一条一次性的 russh 连接，只为了拿到服务器的公钥指纹，随后立即断开。
+/// 真正承载业务流量的连接仍然由 async-ssh2-tokio 的 Client 建立（见
+/// `ssh::SshClient::connect` 如何把这里拿到的指纹继续传给 `ServerCheckMethod::Fingerprint`）。
+async fn fetch_fingerprint(host: &str, port: u16) -> Result<String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let config = std::sync::Arc::new(client::Config::default());
+    let handle: Handle<FingerprintHandler> = client::connect(
+        config,
+        (host, port),
+        FingerprintHandler { fingerprint_tx: Some(tx) },
+    )
+    .await
+    .with_context(|| format!("连接 {}:{} 获取主机密钥失败", host, port))?;
+    drop(handle);
+
+    rx.await.with_context(|| "未能获取服务器公钥指纹")
+}
+
+/// 与 `fetch_fingerprint` 相同，但握手跑在一条已经打通的 channel（通常是跳板
+/// 打开的 direct-tcpip channel）上，而不是新开一条直连 TCP —— 用于 ProxyJump
+/// 场景下校验"跳板后的目标主机"，探测用完即关，不影响后面真正转发业务流量的 channel
+async fn fetch_fingerprint_via_channel(channel: russh::Channel<client::Msg>) -> Result<String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let config = std::sync::Arc::new(client::Config::default());
+    let handle: Handle<FingerprintHandler> = client::connect_stream(
+        config,
+        channel.into_stream(),
+        FingerprintHandler { fingerprint_tx: Some(tx) },
+    )
+    .await
+    .with_context(|| "经跳板获取目标主机密钥失败")?;
+    drop(handle);
+
+    rx.await.with_context(|| "未能获取服务器公钥指纹")
+}
+
+/// 拿到指纹之后的比对/记录逻辑，直连和经跳板两种校验路径共用
+fn compare_and_record(app: &tauri::AppHandle, host: &str, port: u16, fingerprint: String) -> Result<HostKeyStatus> {
+    let pattern = host_pattern(host, port);
+    let mut entries = read_entries(app)?;
+
+    match entries.iter().position(|(p, _)| p == &pattern) {
+        Some(idx) => {
+            let old_fingerprint = entries[idx].1.clone();
+            if old_fingerprint == fingerprint {
+                Ok(HostKeyStatus::Match { fingerprint })
+            } else {
+                Ok(HostKeyStatus::Changed {
+                    old_fingerprint,
+                    new_fingerprint: fingerprint,
+                })
+            }
+        }
+        None => {
+            entries.push((pattern, fingerprint.clone()));
+            write_entries(app, &entries)?;
+            Ok(HostKeyStatus::New { fingerprint })
+        }
+    }
+}
+
+/// TOFU 校验：第一次连接某主机时记录指纹，之后连接时比对指纹是否变化。
+/// 指纹不一致时返回 `HostKeyStatus::Changed`，调用方应拒绝继续连接并提示用户。
+pub async fn verify(app: &tauri::AppHandle, host: &str, port: u16) -> Result<HostKeyStatus> {
+    let fingerprint = fetch_fingerprint(host, port).await?;
+    compare_and_record(app, host, port, fingerprint)
+}
+
+/// ProxyJump 场景下的 TOFU 校验：`channel` 是跳板打开的、指向目标主机的一次性
+/// 探测 channel；校验逻辑和记录的指纹存储都和直连场景完全一样，只是指纹通过
+/// 跳板转发拿到，而不是直连目标主机
+pub async fn verify_via_channel(
+    app: &tauri::AppHandle,
+    host: &str,
+    port: u16,
+    channel: russh::Channel<client::Msg>,
+) -> Result<HostKeyStatus> {
+    let fingerprint = fetch_fingerprint_via_channel(channel).await?;
+    compare_and_record(app, host, port, fingerprint)
+}
+
+/// 列出所有已经被信任（pin）的主机密钥
+pub fn list(app: &tauri::AppHandle) -> Result<Vec<PinnedHostKey>> {
+    Ok(read_entries(app)?
+        .into_iter()
+        .map(|(pattern, fingerprint)| {
+            let (host, port) = split_pattern(&pattern);
+            PinnedHostKey { host, port, fingerprint }
+        })
+        .collect())
+}
+
+/// 删除某个主机的已记录指纹，下次连接时会重新按 TOFU 记录新指纹
+/// （用于处理 JumpServer 主动轮换密钥的场景）
+pub fn forget(app: &tauri::AppHandle, host: &str, port: u16) -> Result<()> {
+    let pattern = host_pattern(host, port);
+    let entries = read_entries(app)?
+        .into_iter()
+        .filter(|(p, _)| p != &pattern)
+        .collect::<Vec<_>>();
+    write_entries(app, &entries)
+}
+
+fn split_pattern(pattern: &str) -> (String, u16) {
+    if let Some(rest) = pattern.strip_prefix('[') {
+        if let Some((host, port)) = rest.split_once("]:") {
+            if let Ok(port) = port.parse() {
+                return (host.to_string(), port);
+            }
+        }
+    }
+    (pattern.to_string(), 22)
+}
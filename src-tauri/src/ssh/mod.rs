@@ -1,6 +1,27 @@
 use async_ssh2_tokio::{Client, AuthMethod, ServerCheckMethod};
-use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
 use anyhow::{Result, Context};
+use uuid::Uuid;
+
+mod shell;
+mod jump;
+mod known_hosts;
+mod stream_exec;
+mod sudo_exec;
+pub mod agent;
+pub use shell::PtySize;
+pub use known_hosts::{HostKeyStatus, PinnedHostKey};
+
+/// 列出所有已经被信任（pin）的主机密钥
+pub fn known_hosts_list(app: &tauri::AppHandle) -> Result<Vec<PinnedHostKey>> {
+    known_hosts::list(app)
+}
+
+/// 清除某个主机已记录的指纹，下次连接时会重新按 TOFU 记录新指纹
+pub fn known_hosts_forget(app: &tauri::AppHandle, host: &str, port: u16) -> Result<()> {
+    known_hosts::forget(app, host, port)
+}
 
 #[derive(Clone)]
 pub struct SshConfig {
@@ -9,38 +30,112 @@ pub struct SshConfig {
     pub username: String,
     pub password: Option<String>,
     pub key_file: Option<String>,
+    // 是否尝试使用运行中的 SSH agent（SSH_AUTH_SOCK / Windows OpenSSH 命名管道）
+    pub use_agent: bool,
+    // 有序的跳板机链（ProxyJump），依次逐跳建立连接，最后一跳转发到本配置的目标
+    pub jump_hosts: Vec<SshConfig>,
+    // 是否对目标主机做 known_hosts 指纹校验（TOFU）。JumpServer 密钥轮换等场景
+    // 可以先调用 ssh_forget_known_host 清除旧指纹，再以此开关重新信任新密钥
+    pub verify_host_key: bool,
 }
 
 pub struct SshClient;
 
-static SSH_CLIENT: Mutex<Option<Arc<Client>>> = Mutex::new(None);
-static SSH_CONFIG: Mutex<Option<SshConfig>> = Mutex::new(None);
+// 会话条目：除了客户端和配置外，还持有跳板链的后台转发任务（如果有），
+// 保证转发任务和会话本身一样长命
+struct Session {
+    client: Arc<Client>,
+    config: SshConfig,
+    tunnel: Option<tokio::task::JoinHandle<()>>,
+}
+
+// 会话注册表：支持同时持有多个 SSH 连接（例如同时操作多个 JumpServer 目标）
+static SESSIONS: OnceLock<Mutex<HashMap<String, Session>>> = OnceLock::new();
+
+fn sessions() -> &'static Mutex<HashMap<String, Session>> {
+    SESSIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
 
 impl SshClient {
     /// 连接到 SSH 服务器（类似 paramiko 的连接方式，针对 JumpServer 优化）
-    pub async fn connect(config: SshConfig) -> Result<()> {
-        let addr = (&config.host[..], config.port);
-        
+    /// 成功后返回本次连接的会话 ID，后续操作都通过该 ID 寻址。
+    /// 如果配置了 `jump_hosts`，会依次经每一跳建立转发通道，效果等同于 `ssh -J`。
+    /// 如果 `verify_host_key` 为真，会在正式连接前按 TOFU 策略比对目标主机的密钥指纹，
+    /// 指纹发生变化时直接拒绝连接（跳板机各跳暂不纳入校验范围，只校验最终目标）；
+    /// 校验到的指纹会继续 pin 到下面真正承载业务流量的连接上，而不是校验完就丢弃——
+    /// 否则一个能通过探测握手的中间人仍能在正式连接时替换成另一把密钥。
+    pub async fn connect(app: &tauri::AppHandle, config: SshConfig) -> Result<String> {
+        let mut pinned_fingerprint: Option<String> = None;
+
+        if config.verify_host_key && config.jump_hosts.is_empty() {
+            match known_hosts::verify(app, &config.host, config.port).await? {
+                HostKeyStatus::New { fingerprint } => {
+                    eprintln!("首次连接 {}:{}，已记录主机密钥指纹: {}", config.host, config.port, fingerprint);
+                    pinned_fingerprint = Some(fingerprint);
+                }
+                HostKeyStatus::Match { fingerprint } => pinned_fingerprint = Some(fingerprint),
+                HostKeyStatus::Changed { old_fingerprint, new_fingerprint } => {
+                    anyhow::bail!(
+                        "主机密钥已改变，拒绝连接（可能是密钥轮换，也可能是中间人攻击）\n\n\
+                        主机: {}:{}\n旧指纹: {}\n新指纹: {}\n\n\
+                        如果确认是服务器密钥轮换，请先调用 ssh_forget_known_host 清除旧记录后重试",
+                        config.host, config.port, old_fingerprint, new_fingerprint
+                    );
+                }
+            }
+        }
+
+
+        // 尝试使用运行中的 SSH agent（类似 paramiko 的 allow_agent=True）
+        // 注意：async-ssh2-tokio 的 AuthMethod 目前不支持外部签名的 pubkey 认证，
+        // 这里先枚举 agent 身份用于日志/诊断，真正的免落盘签名由 vault 的
+        // in-process agent 端点（见 crate::vault::serve_as_agent）对外提供
+        if config.use_agent {
+            match agent::list_identities().await {
+                Ok(identities) => {
+                    eprintln!(
+                        "检测到 SSH agent，可用身份: {}",
+                        identities
+                            .iter()
+                            .map(|i| i.comment.clone())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                }
+                Err(e) => eprintln!("枚举 SSH agent 身份失败: {}", e),
+            }
+        }
+
+        // 如果配置了跳板链，先依次连通每一跳，在最后一跳上开一条转发 channel，
+        // 通过本地回环端口转发出来；之后的认证/连接逻辑就和直连完全一样了，
+        // 只是把目标地址换成了 "127.0.0.1:本地端口"。跳板链本身的后台转发任务
+        // 需要和本次会话一样长命，因此随 SshConfig 一起存进会话注册表。
+        let (addr_host, addr_port, tunnel) = if config.jump_hosts.is_empty() {
+            (config.host.clone(), config.port, None)
+        } else {
+            let (host, port, jump_fingerprint, relay) = jump::open_tunnel(app, &config.jump_hosts, &config).await?;
+            pinned_fingerprint = jump_fingerprint;
+            (host, port, Some(relay))
+        };
+        let addr = (addr_host.as_str(), addr_port);
+
+        // 校验过指纹（直连或经跳板）就 pin 到实际连接上，而不是退回 NoCheck——否则
+        // 校验和真正认证之间的窗口里，中间人仍然可以在这条连接上换一把不同的密钥
+        let server_check = || match &pinned_fingerprint {
+            Some(fingerprint) => ServerCheckMethod::Fingerprint(fingerprint.clone()),
+            None => ServerCheckMethod::NoCheck, // 未开启校验时自动接受服务器密钥（类似 AutoAddPolicy）
+        };
+
         // 尝试使用密钥文件认证（类似 paramiko 的 key_filename）
         // 注意：async-ssh2-tokio 默认只使用指定的认证方法，相当于 paramiko 的
         // look_for_keys=False（不自动查找密钥）和 allow_agent=False（不使用 SSH 代理）
         if let Some(ref key_file) = config.key_file {
             if std::path::Path::new(key_file).exists() {
                 let auth = AuthMethod::with_key_file(key_file, None);
-                match Client::connect(
-                    addr,
-                    &config.username,
-                    auth,
-                    ServerCheckMethod::NoCheck, // 自动接受服务器密钥（类似 AutoAddPolicy）
-                )
-                    .await
-                {
+                match Client::connect(addr, &config.username, auth, server_check()).await {
                     Ok(client) => {
                         // 密钥认证成功
-                        let client_arc = Arc::new(client);
-                        *SSH_CLIENT.lock().unwrap() = Some(client_arc);
-                        *SSH_CONFIG.lock().unwrap() = Some(config);
-                        return Ok(());
+                        return Ok(Self::register_session(client, config, tunnel));
                     }
                     Err(e) => {
                         eprintln!("密钥认证失败: {}, 尝试密码认证", e);
@@ -49,25 +144,17 @@ impl SshClient {
                 }
             }
         }
-        
+
         // 使用密码认证（类似 paramiko 的 password）
         // 注意：AuthMethod::with_password() 只使用密码认证，不会尝试密钥或代理
         // 这相当于 paramiko 的 look_for_keys=False 和 allow_agent=False（对 JumpServer 很重要）
         if let Some(ref password) = config.password {
             let auth = AuthMethod::with_password(password);
-            let client = Client::connect(
-                addr,
-                &config.username,
-                auth,
-                ServerCheckMethod::NoCheck, // 自动接受服务器密钥（类似 AutoAddPolicy）
-            )
+            let client = Client::connect(addr, &config.username, auth, server_check())
                 .await
-            .with_context(|| format!("SSH 连接失败: {}@{}:{}", config.username, config.host, config.port))?;
-            
-                let client_arc = Arc::new(client);
-                *SSH_CLIENT.lock().unwrap() = Some(client_arc);
-            *SSH_CONFIG.lock().unwrap() = Some(config);
-                Ok(())
+                .with_context(|| format!("SSH 连接失败: {}@{}:{}", config.username, config.host, config.port))?;
+
+            Ok(Self::register_session(client, config, tunnel))
         } else {
             anyhow::bail!(
                 "缺少认证信息\n\n\
@@ -79,35 +166,75 @@ impl SshClient {
         }
     }
 
-    /// 断开 SSH 连接
-    pub async fn disconnect() {
-        if let Some(client) = SSH_CLIENT.lock().unwrap().take() {
-            // Client 在 Drop 时会自动关闭连接
-            drop(client);
+    /// 将已建立的客户端注册为一个新会话，返回会话 ID。
+    /// `tunnel` 是跳板链的后台转发任务（无跳板时为 None），随会话一起持有。
+    fn register_session(client: Client, config: SshConfig, tunnel: Option<tokio::task::JoinHandle<()>>) -> String {
+        let session_id = Uuid::new_v4().to_string();
+        sessions().lock().unwrap().insert(
+            session_id.clone(),
+            Session {
+                client: Arc::new(client),
+                config,
+                tunnel,
+            },
+        );
+        session_id
+    }
+
+    /// 断开指定会话的 SSH 连接
+    pub async fn disconnect(session_id: &str) {
+        if let Some(session) = sessions().lock().unwrap().remove(session_id) {
+            // Client 在 Drop 时会自动关闭连接；跳板转发任务需要主动 abort
+            if let Some(tunnel) = session.tunnel {
+                tunnel.abort();
+            }
+            drop(session.client);
+        }
+    }
+
+    /// 获取指定会话的 SSH 客户端
+    pub fn get_client(session_id: &str) -> Result<Arc<Client>> {
+        sessions()
+            .lock()
+            .unwrap()
+            .get(session_id)
+            .map(|session| session.client.clone())
+            .ok_or_else(|| anyhow::anyhow!("会话不存在或已断开: {}", session_id))
+    }
+
+    /// 是否还有会话处于连接状态，关闭主窗口前用于决定要不要弹确认框
+    pub fn has_active_sessions() -> bool {
+        !sessions().lock().unwrap().is_empty()
+    }
+
+    /// 断开所有当前持有的会话，退出应用前调用，避免远程连接泄漏
+    pub async fn disconnect_all() {
+        let ids: Vec<String> = sessions().lock().unwrap().keys().cloned().collect();
+        for id in ids {
+            Self::disconnect(&id).await;
         }
-        *SSH_CONFIG.lock().unwrap() = None;
     }
 
-    /// 获取 SSH 客户端
-    pub fn get_client() -> Result<Arc<Client>> {
-        SSH_CLIENT
+    /// 获取指定会话建立时使用的配置
+    pub fn get_config(session_id: &str) -> Result<SshConfig> {
+        sessions()
             .lock()
             .unwrap()
-            .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("未连接，请先调用 connect()"))
-            .map(|c| c.clone())
+            .get(session_id)
+            .map(|session| session.config.clone())
+            .ok_or_else(|| anyhow::anyhow!("会话不存在或已断开: {}", session_id))
     }
 
     /// 执行 SSH 命令（类似 paramiko 的 exec_command）
-    pub async fn execute_command(command: &str) -> Result<(i32, String, String)> {
-        let client = Self::get_client()?;
-        
+    pub async fn execute_command(session_id: &str, command: &str) -> Result<(i32, String, String)> {
+        let client = Self::get_client(session_id)?;
+
         // 执行命令（async-ssh2-tokio 提供了便捷的 execute 方法）
         let result = client
             .execute(command)
             .await
             .with_context(|| format!("执行命令失败: {}", command))?;
-        
+
         Ok((
             result.exit_status as i32,
             result.stdout,
@@ -116,28 +243,28 @@ impl SshClient {
     }
 
     /// 上传文件到远程服务器（使用 SFTP，类似 paramiko 的 put）
-    pub async fn upload_file(local_path: &str, remote_path: &str) -> Result<()> {
-        let client = Self::get_client()?;
-        
+    pub async fn upload_file(session_id: &str, local_path: &str, remote_path: &str) -> Result<()> {
+        let client = Self::get_client(session_id)?;
+
         // upload_file(本地路径, 远程路径, 权限, 块大小, 是否覆盖)
         client
             .upload_file(local_path, remote_path, None, None, true)
             .await
             .with_context(|| format!("上传文件失败: {} -> {}", local_path, remote_path))?;
-        
+
         Ok(())
     }
 
     /// 从远程服务器下载文件（使用 SFTP，类似 paramiko 的 get）
-    pub async fn download_file(remote_path: &str, local_path: &str) -> Result<()> {
-        let client = Self::get_client()?;
-        
+    pub async fn download_file(session_id: &str, remote_path: &str, local_path: &str) -> Result<()> {
+        let client = Self::get_client(session_id)?;
+
         // download_file(远程路径, 本地路径)
         client
             .download_file(remote_path, local_path)
             .await
             .with_context(|| format!("下载文件失败: {} -> {}", remote_path, local_path))?;
-        
+
         Ok(())
     }
 }
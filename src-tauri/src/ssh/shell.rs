@@ -0,0 +1,217 @@
+use super::SshClient;
+use anyhow::{Context, Result};
+use russh::client::{self, Handle};
+use russh::{ChannelId, ChannelMsg, Disconnect};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use tauri::Emitter;
+use tokio::sync::{mpsc, oneshot};
+use uuid::Uuid;
+
+/// PTY 尺寸，类似 wezterm_ssh 里的 PtySize
+#[derive(Clone, Copy, Debug)]
+pub struct PtySize {
+    pub rows: u16,
+    pub cols: u16,
+}
+
+impl Default for PtySize {
+    fn default() -> Self {
+        // 常见终端默认尺寸
+        Self { rows: 24, cols: 80 }
+    }
+}
+
+enum ShellCommand {
+    Write(Vec<u8>),
+    Resize(PtySize),
+}
+
+struct ShellHandle {
+    command_tx: mpsc::UnboundedSender<ShellCommand>,
+    close_tx: Option<oneshot::Sender<()>>,
+}
+
+// 交互式 shell 句柄注册表，key 为 shell ID
+static SHELLS: OnceLock<Mutex<HashMap<String, ShellHandle>>> = OnceLock::new();
+
+fn shells() -> &'static Mutex<HashMap<String, ShellHandle>> {
+    SHELLS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// async-ssh2-tokio 的 Client 只暴露了 execute/upload/download 这类高层接口，
+// 没有提供原始 channel，无法用来驱动 PTY。这里直接用 russh 针对该会话的配置
+// 重新建立一条底层连接，专门承载交互式 shell。
+pub(super) struct ShellAuthHandler;
+
+#[async_trait::async_trait]
+impl client::Handler for ShellAuthHandler {
+    type Error = russh::Error;
+
+    async fn check_server_key(&mut self, _server_public_key: &russh_keys::key::PublicKey) -> Result<bool, Self::Error> {
+        // 自动接受服务器密钥（类似 AutoAddPolicy），不做 known_hosts 校验：即使会话开启了
+        // verify_host_key，这条 russh 专用连接也不比对 SshClient::connect pin 住的指纹
+        Ok(true)
+    }
+}
+
+/// 在一条已建立（但未认证）的 russh 连接上执行密钥优先、密码兜底的认证，
+/// 供交互式 shell 和跳板链（见 `super::jump`）共用
+pub(super) async fn authenticate(handle: &mut Handle<ShellAuthHandler>, config: &super::SshConfig) -> Result<()> {
+    if let Some(ref key_file) = config.key_file {
+        if std::path::Path::new(key_file).exists() {
+            if let Ok(key_pair) = russh_keys::load_secret_key(key_file, None) {
+                if handle
+                    .authenticate_publickey(&config.username, std::sync::Arc::new(key_pair))
+                    .await
+                    .unwrap_or(false)
+                {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    if let Some(ref password) = config.password {
+        if handle.authenticate_password(&config.username, password).await? {
+            return Ok(());
+        }
+    }
+
+    anyhow::bail!(
+        "认证失败：{}@{}:{} 密钥和密码认证均未通过",
+        config.username, config.host, config.port
+    )
+}
+
+impl SshClient {
+    /// 打开一个带 PTY 的交互式 shell，返回 shell 句柄 ID。
+    /// stdout/stderr 会通过 `shell-data-{shell_id}` 事件持续发送给前端，
+    /// 直到调用 shell_close 或连接断开。
+    pub async fn open_shell(
+        app: tauri::AppHandle,
+        session_id: &str,
+        size: PtySize,
+        term: &str,
+    ) -> Result<String> {
+        let config = SshClient::get_config(session_id)?;
+        let term = term.to_string();
+
+        let russh_config = std::sync::Arc::new(client::Config::default());
+        let mut handle = client::connect(russh_config, (config.host.as_str(), config.port), ShellAuthHandler)
+            .await
+            .with_context(|| format!("打开交互式 shell 连接失败: {}:{}", config.host, config.port))?;
+
+        authenticate(&mut handle, &config).await?;
+
+        let mut channel = handle
+            .channel_open_session()
+            .await
+            .with_context(|| "打开 shell channel 失败")?;
+
+        channel
+            .request_pty(false, &term, size.cols as u32, size.rows as u32, 0, 0, &[])
+            .await
+            .with_context(|| "请求 PTY 失败")?;
+        channel
+            .request_shell(false)
+            .await
+            .with_context(|| "请求交互式 shell 失败")?;
+
+        let shell_id = Uuid::new_v4().to_string();
+        let (command_tx, mut command_rx) = mpsc::unbounded_channel::<ShellCommand>();
+        let (close_tx, mut close_rx) = oneshot::channel::<()>();
+
+        let event_shell_id = shell_id.clone();
+        let channel_id: ChannelId = channel.id();
+
+        // 读取循环：持续读取远端输出并通过事件发送给前端；同时接收写入/resize 指令
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    msg = channel.wait() => {
+                        match msg {
+                            Some(ChannelMsg::Data { data }) => {
+                                let _ = app.emit(&format!("shell-data-{}", event_shell_id), data.to_vec());
+                            }
+                            Some(ChannelMsg::ExtendedData { data, .. }) => {
+                                let _ = app.emit(&format!("shell-stderr-{}", event_shell_id), data.to_vec());
+                            }
+                            Some(ChannelMsg::ExitStatus { exit_status }) => {
+                                let _ = app.emit(&format!("shell-exit-{}", event_shell_id), exit_status);
+                            }
+                            Some(ChannelMsg::Eof) | None => break,
+                            _ => {}
+                        }
+                    }
+                    cmd = command_rx.recv() => {
+                        match cmd {
+                            Some(ShellCommand::Write(bytes)) => {
+                                let _ = channel.data(bytes.as_slice()).await;
+                            }
+                            Some(ShellCommand::Resize(size)) => {
+                                let _ = channel
+                                    .window_change(size.cols as u32, size.rows as u32, 0, 0)
+                                    .await;
+                            }
+                            None => break,
+                        }
+                    }
+                    _ = &mut close_rx => {
+                        let _ = channel.close().await;
+                        break;
+                    }
+                }
+            }
+            let _ = handle.disconnect(Disconnect::ByApplication, "", "").await;
+            shells().lock().unwrap().remove(&event_shell_id);
+        });
+
+        shells().lock().unwrap().insert(
+            shell_id.clone(),
+            ShellHandle {
+                command_tx,
+                close_tx: Some(close_tx),
+            },
+        );
+        let _ = channel_id;
+
+        Ok(shell_id)
+    }
+
+    /// 向交互式 shell 写入数据（键盘输入）
+    pub fn shell_write(shell_id: &str, bytes: Vec<u8>) -> Result<()> {
+        let guard = shells().lock().unwrap();
+        let handle = guard
+            .get(shell_id)
+            .ok_or_else(|| anyhow::anyhow!("shell 不存在或已关闭: {}", shell_id))?;
+        handle
+            .command_tx
+            .send(ShellCommand::Write(bytes))
+            .map_err(|_| anyhow::anyhow!("shell 读取循环已退出: {}", shell_id))
+    }
+
+    /// 调整交互式 shell 的 PTY 尺寸
+    pub fn shell_resize(shell_id: &str, cols: u16, rows: u16) -> Result<()> {
+        let guard = shells().lock().unwrap();
+        let handle = guard
+            .get(shell_id)
+            .ok_or_else(|| anyhow::anyhow!("shell 不存在或已关闭: {}", shell_id))?;
+        handle
+            .command_tx
+            .send(ShellCommand::Resize(PtySize { rows, cols }))
+            .map_err(|_| anyhow::anyhow!("shell 读取循环已退出: {}", shell_id))
+    }
+
+    /// 关闭交互式 shell
+    pub fn shell_close(shell_id: &str) -> Result<()> {
+        let mut guard = shells().lock().unwrap();
+        let mut handle = guard
+            .remove(shell_id)
+            .ok_or_else(|| anyhow::anyhow!("shell 不存在或已关闭: {}", shell_id))?;
+        if let Some(close_tx) = handle.close_tx.take() {
+            let _ = close_tx.send(());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use super::shell::{authenticate, ShellAuthHandler};
+use super::SshClient;
+use anyhow::{Context, Result};
+use russh::client::{self};
+use russh::ChannelMsg;
+use tauri::{AppHandle, Emitter};
+
+impl SshClient {
+    /// 流式执行命令：stdout/stderr 一到达就通过事件推给前端，命令结束后再发一个
+    /// 携带退出码的事件，而不是像 `execute_command` 那样等命令跑完再一次性返回。
+    /// 用于部署这类耗时较长、需要让用户看到实时进度的命令。
+    ///
+    /// async-ssh2-tokio 的 `execute` 只会在命令结束后返回完整输出，没有增量读取的
+    /// 接口，这里和交互式 shell 一样直接用 russh 建一条专用连接来驱动 exec channel。
+    ///
+    /// `sudo_password` 为 `None` 时 `command` 原样执行（沿用无密码 sudo 的默认行为）；
+    /// 传入密码时会把 `command` 里的 `sudo ` 替换成 `sudo -S -p ''` 并把密码写进
+    /// stdin，用法和 `SshClient::execute_sudo` 一致，只是输出走的是流式事件而不是
+    /// 一次性返回值。
+    pub async fn execute_streaming(
+        session_id: &str,
+        command: &str,
+        app: &AppHandle,
+        sudo_password: Option<&str>,
+    ) -> Result<i32> {
+        let config = SshClient::get_config(session_id)?;
+
+        let russh_config = std::sync::Arc::new(client::Config::default());
+        let mut handle = client::connect(russh_config, (config.host.as_str(), config.port), ShellAuthHandler)
+            .await
+            .with_context(|| format!("流式执行连接失败: {}:{}", config.host, config.port))?;
+
+        authenticate(&mut handle, &config).await?;
+
+        let mut channel = handle
+            .channel_open_session()
+            .await
+            .with_context(|| "打开 exec channel 失败")?;
+
+        let exec_command = match sudo_password {
+            Some(_) => super::sudo_exec::with_sudo_stdin(command),
+            None => command.to_string(),
+        };
+        channel
+            .exec(true, exec_command.as_str())
+            .await
+            .with_context(|| format!("执行命令失败: {}", command))?;
+
+        if let Some(password) = sudo_password {
+            channel
+                .data(format!("{}\n", password).as_bytes())
+                .await
+                .with_context(|| "写入 sudo 密码失败")?;
+        }
+
+        let stdout_event = format!("exec-stdout-{}", session_id);
+        let stderr_event = format!("exec-stderr-{}", session_id);
+        let exit_event = format!("exec-exit-{}", session_id);
+
+        let mut exit_status: i32 = -1;
+        loop {
+            match channel.wait().await {
+                Some(ChannelMsg::Data { data }) => {
+                    let _ = app.emit(&stdout_event, String::from_utf8_lossy(&data).to_string());
+                }
+                Some(ChannelMsg::ExtendedData { data, .. }) => {
+                    let _ = app.emit(&stderr_event, String::from_utf8_lossy(&data).to_string());
+                }
+                Some(ChannelMsg::ExitStatus { exit_status: status }) => {
+                    exit_status = status as i32;
+                }
+                Some(ChannelMsg::Eof) | None => break,
+                _ => {}
+            }
+        }
+
+        let _ = app.emit(&exit_event, exit_status);
+        let _ = handle.disconnect(russh::Disconnect::ByApplication, "", "").await;
+
+        Ok(exit_status)
+    }
+}
@@ -0,0 +1,95 @@
+use super::shell::{authenticate, ShellAuthHandler};
+use super::SshClient;
+use anyhow::{Context, Result};
+use russh::client::{self};
+use russh::{ChannelMsg, Disconnect};
+
+// 把组合命令（如 "sudo a && sudo b"）里每一段 "sudo " 换成 "sudo -S -p ''"，
+// 让每一段都从 stdin 读取密码、且不打印自带的提示符（提示符留给上层自己处理）。
+// `execute_streaming` 的流式路径也复用这个转换，保持两条路径的行为一致。
+pub(super) fn with_sudo_stdin(command: &str) -> String {
+    command.replace("sudo ", "sudo -S -p '' ")
+}
+
+// sudo 密码错误 / 需要密码却没提供时，sudo -S 打在 stderr 上的典型提示
+const SUDO_AUTH_FAILURE_PATTERNS: &[&str] = &[
+    "incorrect password",
+    "sorry, try again",
+    "a password is required",
+    "no password was provided",
+];
+
+fn is_sudo_auth_failure(stderr: &str) -> bool {
+    let lower = stderr.to_lowercase();
+    SUDO_AUTH_FAILURE_PATTERNS.iter().any(|p| lower.contains(p))
+}
+
+impl SshClient {
+    /// 执行一条已经按现有约定拼好 `sudo ...` 前缀的命令。
+    /// `sudo_password` 为 `None` 时完全退化为原有的无密码行为（要求目标主机
+    /// 配置了 NOPASSWD），这是默认路径；传入密码后改走 `sudo -S -p ''`，
+    /// 把密码通过 stdin 喂给 sudo，并把"密码错误"识别成一个独立的错误，方便
+    /// 上层区分"命令本身失败"和"需要重新输入密码"。
+    ///
+    /// 密码本身不会出现在 `command` 里，因此调用方打印/落盘这条命令时不需要
+    /// 额外脱敏。
+    pub async fn execute_sudo(
+        session_id: &str,
+        command: &str,
+        sudo_password: Option<&str>,
+    ) -> Result<(i32, String, String)> {
+        let Some(password) = sudo_password else {
+            return Self::execute_command(session_id, command).await;
+        };
+
+        let config = Self::get_config(session_id)?;
+        let sudo_command = with_sudo_stdin(command);
+
+        let russh_config = std::sync::Arc::new(client::Config::default());
+        let mut handle = client::connect(russh_config, (config.host.as_str(), config.port), ShellAuthHandler)
+            .await
+            .with_context(|| format!("sudo 执行连接失败: {}:{}", config.host, config.port))?;
+
+        authenticate(&mut handle, &config).await?;
+
+        let mut channel = handle
+            .channel_open_session()
+            .await
+            .with_context(|| "打开 exec channel 失败")?;
+
+        channel
+            .exec(true, sudo_command.as_str())
+            .await
+            .with_context(|| format!("执行命令失败: {}", command))?;
+
+        // sudo -S 只在第一次提权时从 stdin 读一行密码；组合命令里即使有多个
+        // sudo 段，同一个已缓存的提权凭证也能让后续段跳过再次提示
+        channel
+            .data(format!("{}\n", password).as_bytes())
+            .await
+            .with_context(|| "写入 sudo 密码失败")?;
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let mut exit_status: i32 = -1;
+        loop {
+            match channel.wait().await {
+                Some(ChannelMsg::Data { data }) => stdout.extend_from_slice(&data),
+                Some(ChannelMsg::ExtendedData { data, .. }) => stderr.extend_from_slice(&data),
+                Some(ChannelMsg::ExitStatus { exit_status: status }) => exit_status = status as i32,
+                Some(ChannelMsg::Eof) | None => break,
+                _ => {}
+            }
+        }
+        let _ = handle.disconnect(Disconnect::ByApplication, "", "").await;
+
+        let stdout = String::from_utf8_lossy(&stdout).to_string();
+        let stderr = String::from_utf8_lossy(&stderr).to_string();
+
+        if is_sudo_auth_failure(&stderr) {
+            anyhow::bail!("sudo 密码错误，请重新输入");
+        }
+
+        Ok((exit_status, stdout, stderr))
+    }
+}
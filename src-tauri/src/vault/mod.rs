@@ -0,0 +1,382 @@
+use anyhow::{Context, Result};
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::Serialize;
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::{Row, SqlitePool};
+use std::sync::OnceLock;
+use tokio::sync::Mutex as AsyncMutex;
+
+// 用于验证解密密钥是否正确的已知明文（canary）
+const CANARY_PLAINTEXT: &[u8] = b"remote-tool-vault-canary-v1";
+
+/// 解锁后派生出的密钥，常驻内存直到进程退出（每个会话只需解锁一次）
+static VAULT_KEY: OnceLock<AsyncMutex<Option<[u8; 32]>>> = OnceLock::new();
+static VAULT_POOL: OnceLock<AsyncMutex<Option<SqlitePool>>> = OnceLock::new();
+
+fn vault_key_slot() -> &'static AsyncMutex<Option<[u8; 32]>> {
+    VAULT_KEY.get_or_init(|| AsyncMutex::new(None))
+}
+
+fn vault_pool_slot() -> &'static AsyncMutex<Option<SqlitePool>> {
+    VAULT_POOL.get_or_init(|| AsyncMutex::new(None))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialSummary {
+    pub host: String,
+    pub username: String,
+    pub kind: String,
+}
+
+fn vault_db_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf> {
+    use tauri::Manager;
+    let dir = app
+        .path()
+        .app_data_dir()
+        .with_context(|| "无法解析应用数据目录")?;
+    std::fs::create_dir_all(&dir).with_context(|| format!("创建应用数据目录失败: {:?}", dir))?;
+    Ok(dir.join("vault.db"))
+}
+
+async fn open_pool(app: &tauri::AppHandle) -> Result<SqlitePool> {
+    let db_path = vault_db_path(app)?;
+    let url = format!("sqlite://{}?mode=rwc", db_path.to_string_lossy());
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(&url)
+        .await
+        .with_context(|| format!("打开凭据库失败: {:?}", db_path))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS vault_meta (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            salt BLOB NOT NULL,
+            canary_nonce BLOB NOT NULL,
+            canary_ciphertext BLOB NOT NULL
+        )",
+    )
+    .execute(&pool)
+    .await
+    .with_context(|| "创建 vault_meta 表失败")?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS credentials (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            host TEXT NOT NULL,
+            username TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            nonce BLOB NOT NULL,
+            ciphertext BLOB NOT NULL,
+            UNIQUE(host, username)
+        )",
+    )
+    .execute(&pool)
+    .await
+    .with_context(|| "创建 credentials 表失败")?;
+
+    Ok(pool)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("密钥派生失败: {}", e))?;
+    Ok(key)
+}
+
+fn seal(key: &[u8; 32], plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key));
+    let mut nonce_bytes = [0u8; 24];
+    rand::thread_rng().fill_bytes(&mut nonce_bytes);
+    let nonce = XNonce::from_slice(&nonce_bytes);
+    let ciphertext = cipher
+        .encrypt(nonce, plaintext)
+        .map_err(|e| anyhow::anyhow!("加密失败: {}", e))?;
+    Ok((nonce_bytes.to_vec(), ciphertext))
+}
+
+fn open(key: &[u8; 32], nonce: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key));
+    cipher
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow::anyhow!("解密失败，密码可能不正确"))
+}
+
+/// 解锁凭据库：首次运行时用主密码派生密钥并写入 canary，之后每次验证该 canary
+pub async fn unlock(app: tauri::AppHandle, passphrase: String) -> Result<()> {
+    let pool = open_pool(&app).await?;
+
+    let meta_row = sqlx::query("SELECT salt, canary_nonce, canary_ciphertext FROM vault_meta WHERE id = 1")
+        .fetch_optional(&pool)
+        .await
+        .with_context(|| "读取 vault_meta 失败")?;
+
+    let key = if let Some(row) = meta_row {
+        let salt: Vec<u8> = row.get("salt");
+        let canary_nonce: Vec<u8> = row.get("canary_nonce");
+        let canary_ciphertext: Vec<u8> = row.get("canary_ciphertext");
+
+        let key = derive_key(&passphrase, &salt)?;
+        let decrypted = open(&key, &canary_nonce, &canary_ciphertext)?;
+        if decrypted != CANARY_PLAINTEXT {
+            anyhow::bail!("主密码不正确");
+        }
+        key
+    } else {
+        // 首次使用：生成随机盐并写入 canary
+        let mut salt = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let key = derive_key(&passphrase, &salt)?;
+        let (canary_nonce, canary_ciphertext) = seal(&key, CANARY_PLAINTEXT)?;
+
+        sqlx::query(
+            "INSERT INTO vault_meta (id, salt, canary_nonce, canary_ciphertext) VALUES (1, ?, ?, ?)",
+        )
+        .bind(salt.to_vec())
+        .bind(canary_nonce)
+        .bind(canary_ciphertext)
+        .execute(&pool)
+        .await
+        .with_context(|| "初始化 vault_meta 失败")?;
+
+        key
+    };
+
+    *vault_key_slot().lock().await = Some(key);
+    *vault_pool_slot().lock().await = Some(pool);
+    Ok(())
+}
+
+async fn unlocked_key() -> Result<[u8; 32]> {
+    vault_key_slot()
+        .lock()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("凭据库尚未解锁，请先调用 vault_unlock"))
+}
+
+async fn pool() -> Result<SqlitePool> {
+    vault_pool_slot()
+        .lock()
+        .await
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("凭据库尚未解锁，请先调用 vault_unlock"))
+}
+
+/// 保存一条凭据（密码或私钥内容），加密后写入库中
+pub async fn save_credential(host: String, username: String, kind: String, secret: String) -> Result<()> {
+    let key = unlocked_key().await?;
+    let pool = pool().await?;
+
+    let (nonce, ciphertext) = seal(&key, secret.as_bytes())?;
+
+    sqlx::query(
+        "INSERT INTO credentials (host, username, kind, nonce, ciphertext) VALUES (?, ?, ?, ?, ?)
+         ON CONFLICT(host, username) DO UPDATE SET kind = excluded.kind, nonce = excluded.nonce, ciphertext = excluded.ciphertext",
+    )
+    .bind(&host)
+    .bind(&username)
+    .bind(&kind)
+    .bind(nonce)
+    .bind(ciphertext)
+    .execute(&pool)
+    .await
+    .with_context(|| format!("保存凭据失败: {}@{}", username, host))?;
+
+    Ok(())
+}
+
+/// 列出已保存的凭据（不含明文，仅用于 UI 展示）
+pub async fn list() -> Result<Vec<CredentialSummary>> {
+    let pool = pool().await?;
+    let rows = sqlx::query("SELECT host, username, kind FROM credentials ORDER BY host, username")
+        .fetch_all(&pool)
+        .await
+        .with_context(|| "读取凭据列表失败")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| CredentialSummary {
+            host: row.get("host"),
+            username: row.get("username"),
+            kind: row.get("kind"),
+        })
+        .collect())
+}
+
+/// 以 in-process SSH agent 的身份对外提供库中已保存的私钥，签名在内存中完成，
+/// 私钥明文不会落盘。返回值可以直接赋给 SSH_AUTH_SOCK，供 JumpServer 跳板间的
+/// agent forwarding 使用。
+/// 依赖 crate::ssh::agent 的协议常量和 russh_keys，因此需要 ssh feature；
+/// 唯一的调用方 commands::vault_serve_as_agent 已经是同样的 cfg，关掉 ssh
+/// feature 时这个函数（以及下面两个 handler）整个不参与编译。
+#[cfg(feature = "ssh")]
+pub async fn serve_as_agent() -> Result<String> {
+    #[cfg(not(unix))]
+    {
+        anyhow::bail!("当前平台暂不支持 vault 内置 agent 端点")
+    }
+
+    #[cfg(unix)]
+    {
+        use crate::ssh::agent::{SSH_AGENTC_REQUEST_IDENTITIES, SSH_AGENTC_SIGN_REQUEST};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::UnixListener;
+
+        let socket_path = std::env::temp_dir().join(format!("remote-tool-vault-agent-{}.sock", uuid::Uuid::new_v4()));
+        let _ = std::fs::remove_file(&socket_path);
+        let listener = UnixListener::bind(&socket_path)
+            .with_context(|| format!("绑定 vault agent socket 失败: {:?}", socket_path))?;
+
+        let socket_path_str = socket_path.to_string_lossy().to_string();
+
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else { break };
+                tokio::spawn(async move {
+                    loop {
+                        let mut len_buf = [0u8; 4];
+                        if stream.read_exact(&mut len_buf).await.is_err() {
+                            break;
+                        }
+                        let len = u32::from_be_bytes(len_buf) as usize;
+                        let mut body = vec![0u8; len];
+                        if stream.read_exact(&mut body).await.is_err() {
+                            break;
+                        }
+                        let Some(&msg_type) = body.first() else { break };
+
+                        let response = match msg_type {
+                            SSH_AGENTC_REQUEST_IDENTITIES => handle_list_identities().await,
+                            SSH_AGENTC_SIGN_REQUEST => handle_sign_request(&body[1..]).await,
+                            _ => Ok(vec![5]), // SSH_AGENT_FAILURE = 5
+                        };
+
+                        let response = response.unwrap_or_else(|_| vec![5]); // SSH_AGENT_FAILURE
+                        let mut frame = (response.len() as u32).to_be_bytes().to_vec();
+                        frame.extend_from_slice(&response);
+                        if stream.write_all(&frame).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        Ok(socket_path_str)
+    }
+}
+
+#[cfg(all(unix, feature = "ssh"))]
+async fn handle_list_identities() -> Result<Vec<u8>> {
+    let key = unlocked_key().await?;
+    let pool = pool().await?;
+
+    let rows = sqlx::query("SELECT nonce, ciphertext FROM credentials WHERE kind = 'key'")
+        .fetch_all(&pool)
+        .await?;
+
+    let mut out = vec![crate::ssh::agent::SSH_AGENT_IDENTITIES_ANSWER];
+    out.extend_from_slice(&(rows.len() as u32).to_be_bytes());
+
+    for row in rows {
+        let nonce: Vec<u8> = row.get("nonce");
+        let ciphertext: Vec<u8> = row.get("ciphertext");
+        let Ok(secret) = open(&key, &nonce, &ciphertext) else { continue };
+        let Ok(secret) = String::from_utf8(secret) else { continue };
+        let Ok(key_pair) = russh_keys::decode_secret_key(&secret, None) else { continue };
+        let blob = key_pair.clone_public_key()?.public_key_bytes();
+
+        out.extend_from_slice(&(blob.len() as u32).to_be_bytes());
+        out.extend_from_slice(&blob);
+        let comment = b"vault";
+        out.extend_from_slice(&(comment.len() as u32).to_be_bytes());
+        out.extend_from_slice(comment);
+    }
+
+    Ok(out)
+}
+
+/// 解析 SSH_AGENTC_SIGN_REQUEST 的 (key_blob, data, flags) 字段，在库中找到
+/// blob 匹配的私钥，在内存中对 data 签名，按 SSH_AGENT_SIGN_RESPONSE 格式封装返回。
+/// handle_list_identities 列出的身份都来自同一张表，因此这里总能找到对应私钥。
+#[cfg(all(unix, feature = "ssh"))]
+async fn handle_sign_request(payload: &[u8]) -> Result<Vec<u8>> {
+    use crate::ssh::agent::{read_bytes, read_u32, SSH_AGENT_SIGN_RESPONSE};
+
+    let mut cursor = payload;
+    let key_blob = read_bytes(&mut cursor)?;
+    let data = read_bytes(&mut cursor)?;
+    // flags（SSH_AGENT_RSA_SHA2_256/512 等签名算法协商位）暂不支持非默认哈希，
+    // 读出来只是为了让 cursor 正确前进，不影响当前只支持默认签名算法的实现
+    let _flags = read_u32(&mut cursor)?;
+
+    let key = unlocked_key().await?;
+    let pool = pool().await?;
+
+    let rows = sqlx::query("SELECT nonce, ciphertext FROM credentials WHERE kind = 'key'")
+        .fetch_all(&pool)
+        .await?;
+
+    for row in rows {
+        let nonce: Vec<u8> = row.get("nonce");
+        let ciphertext: Vec<u8> = row.get("ciphertext");
+        let Ok(secret) = open(&key, &nonce, &ciphertext) else { continue };
+        let Ok(secret) = String::from_utf8(secret) else { continue };
+        let Ok(key_pair) = russh_keys::decode_secret_key(&secret, None) else { continue };
+        let public_key = key_pair.clone_public_key()?;
+        if public_key.public_key_bytes() != key_blob {
+            continue;
+        }
+
+        let signature = key_pair.sign_detached(&data)?;
+
+        let mut sig_blob = Vec::new();
+        let algo_name = public_key.name().as_bytes();
+        sig_blob.extend_from_slice(&(algo_name.len() as u32).to_be_bytes());
+        sig_blob.extend_from_slice(algo_name);
+        sig_blob.extend_from_slice(&(signature.as_ref().len() as u32).to_be_bytes());
+        sig_blob.extend_from_slice(signature.as_ref());
+
+        let mut out = vec![SSH_AGENT_SIGN_RESPONSE];
+        out.extend_from_slice(&(sig_blob.len() as u32).to_be_bytes());
+        out.extend_from_slice(&sig_blob);
+        return Ok(out);
+    }
+
+    anyhow::bail!("vault 中没有与请求的公钥匹配的私钥")
+}
+
+/// 按 host/username 查找并解密一条凭据，供 ssh_connect 使用
+pub async fn find_credential(host: &str, username: &str) -> Result<Option<(String, String)>> {
+    let key = match vault_key_slot().lock().await.clone() {
+        Some(key) => key,
+        // 凭据库未解锁时静默跳过，回退到 DTO 中显式传入的密码/密钥
+        None => return Ok(None),
+    };
+    let pool = match vault_pool_slot().lock().await.clone() {
+        Some(pool) => pool,
+        None => return Ok(None),
+    };
+
+    let row = sqlx::query("SELECT kind, nonce, ciphertext FROM credentials WHERE host = ? AND username = ?")
+        .bind(host)
+        .bind(username)
+        .fetch_optional(&pool)
+        .await
+        .with_context(|| format!("查找凭据失败: {}@{}", username, host))?;
+
+    let Some(row) = row else { return Ok(None) };
+    let kind: String = row.get("kind");
+    let nonce: Vec<u8> = row.get("nonce");
+    let ciphertext: Vec<u8> = row.get("ciphertext");
+
+    let secret = open(&key, &nonce, &ciphertext)?;
+    let secret = String::from_utf8(secret).with_context(|| "凭据内容不是合法的 UTF-8")?;
+
+    Ok(Some((kind, secret)))
+}